@@ -0,0 +1,271 @@
+use serde::{Serialize, Deserialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use crate::data::DataError;
+use crate::game_state::arena_position;
+use crate::simulator::{BattleOutcome, BattleSimulator, BoardUnit, SimulationError};
+use crate::units::UnitDefinitions;
+
+// 參與平衡測試的一組陣容。位置以自己這一方為準：y = 0 是最前排，數字越大越後排
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Composition {
+    pub name: String,
+    pub units: Vec<BoardUnit>,
+}
+
+impl Composition {
+    // 從 JSON 檔讀取陣容清單
+    pub fn load_file(path: &Path) -> Result<Vec<Composition>, DataError> {
+        let text = std::fs::read_to_string(path).map_err(|error| DataError::Io { path: path.to_path_buf(), error })?;
+        let compositions: Vec<Composition> =
+            serde_json::from_str(&text).map_err(|error| DataError::Parse { path: path.to_path_buf(), error })?;
+        for (index, composition) in compositions.iter().enumerate() {
+            if compositions[..index].iter().any(|other| other.name == composition.name) {
+                return Err(DataError::Invalid {
+                    path: path.to_path_buf(),
+                    id: composition.name.clone(),
+                    reason: "duplicate composition name".to_string(),
+                });
+            }
+        }
+        Ok(compositions)
+    }
+
+    // 每種棋子單獨一個陣容，放在最前排中間
+    pub fn single_units(units: &UnitDefinitions, level: u32) -> Vec<Composition> {
+        units
+            .iter()
+            .map(|definition| Composition {
+                name: definition.id.clone(),
+                units: vec![BoardUnit {
+                    unit_id: definition.id.clone(),
+                    level,
+                    position: (0, 0),
+                    items: Vec::new(),
+                }],
+            })
+            .collect()
+    }
+
+    // 放到模擬的戰鬥場地上，與伺服器使用相同的場地座標
+    fn placed(&self, home: bool) -> Vec<BoardUnit> {
+        let side = if home { 0 } else { 1 };
+        self.units
+            .iter()
+            .map(|unit| BoardUnit { position: arena_position(side, unit.position), ..unit.clone() })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BalanceOptions {
+    pub fights: u32, // 每組對戰的場數
+    pub seed: u64,   // 各場的種子由 seed 與對戰的順序決定，與執行緒數量無關
+    pub threads: usize,
+}
+
+// 一組對戰（主場陣容對客場陣容）的統計，平均值以每場計算
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchupResult {
+    pub home: String,
+    pub away: String,
+    pub fights: u32,
+    pub home_wins: u32,
+    pub away_wins: u32,
+    pub draws: u32,
+    pub win_rate: f32, // 主場勝率，平手算半場
+    pub avg_damage_home: f32,
+    pub avg_damage_away: f32,
+    pub avg_duration_secs: f32,
+    pub avg_time_to_kill_secs: Option<f32>, // 主場獲勝的場次中，消滅對手所需的平均時間
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceReport {
+    pub compositions: Vec<String>,
+    pub fights: u32,
+    pub seed: u64,
+    pub win_rates: Vec<Vec<f32>>, // win_rates[主場][客場]
+    pub matchups: Vec<MatchupResult>,
+}
+
+impl BalanceReport {
+    pub fn matchup(&self, home: usize, away: usize) -> &MatchupResult {
+        &self.matchups[home * self.compositions.len() + away]
+    }
+
+    // 每組對戰一行
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "home,away,fights,home_wins,away_wins,draws,win_rate,avg_damage_home,avg_damage_away,avg_duration_secs,avg_time_to_kill_secs\n",
+        );
+        for m in &self.matchups {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{:.4},{:.1},{:.1},{:.2},{}\n",
+                csv_field(&m.home),
+                csv_field(&m.away),
+                m.fights,
+                m.home_wins,
+                m.away_wins,
+                m.draws,
+                m.win_rate,
+                m.avg_damage_home,
+                m.avg_damage_away,
+                m.avg_duration_secs,
+                m.avg_time_to_kill_secs.map(|t| format!("{:.2}", t)).unwrap_or_default(),
+            ));
+        }
+        csv
+    }
+
+    // 勝率矩陣：列為主場陣容，欄為客場陣容
+    pub fn matrix_csv(&self) -> String {
+        let mut csv = String::from("home\\away");
+        for name in &self.compositions {
+            csv.push(',');
+            csv.push_str(&csv_field(name));
+        }
+        csv.push('\n');
+        for (name, row) in self.compositions.iter().zip(&self.win_rates) {
+            csv.push_str(&csv_field(name));
+            for rate in row {
+                csv.push_str(&format!(",{:.4}", rate));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// 以多個執行緒跑完所有陣容兩兩之間（含同陣容互打）的對戰
+pub fn run_matrix(
+    simulator: &BattleSimulator,
+    compositions: &[Composition],
+    options: BalanceOptions,
+) -> Result<BalanceReport, SimulationError> {
+    let count = compositions.len();
+    let cells = count * count;
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<MatchupResult, SimulationError>>>> =
+        Mutex::new((0..cells).map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..options.threads.max(1).min(cells.max(1)) {
+            scope.spawn(|| loop {
+                let cell = next.fetch_add(1, Ordering::Relaxed);
+                if cell >= cells {
+                    break;
+                }
+                let (home, away) = (&compositions[cell / count], &compositions[cell % count]);
+                let first_seed = options.seed.wrapping_add(cell as u64 * options.fights as u64);
+                let result = run_matchup(simulator, home, away, options.fights, first_seed);
+                results.lock().unwrap()[cell] = Some(result);
+            });
+        }
+    });
+
+    let matchups = results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every matchup is simulated"))
+        .collect::<Result<Vec<_>, _>>()?;
+    let win_rates = matchups.chunks(count.max(1)).map(|row| row.iter().map(|m| m.win_rate).collect()).collect();
+
+    Ok(BalanceReport {
+        compositions: compositions.iter().map(|c| c.name.clone()).collect(),
+        fights: options.fights,
+        seed: options.seed,
+        win_rates,
+        matchups,
+    })
+}
+
+fn run_matchup(
+    simulator: &BattleSimulator,
+    home: &Composition,
+    away: &Composition,
+    fights: u32,
+    first_seed: u64,
+) -> Result<MatchupResult, SimulationError> {
+    let (home_units, away_units) = (home.placed(true), away.placed(false));
+    let mut result = MatchupResult {
+        home: home.name.clone(),
+        away: away.name.clone(),
+        fights,
+        home_wins: 0,
+        away_wins: 0,
+        draws: 0,
+        win_rate: 0.0,
+        avg_damage_home: 0.0,
+        avg_damage_away: 0.0,
+        avg_duration_secs: 0.0,
+        avg_time_to_kill_secs: None,
+    };
+    let (mut damage, mut duration, mut time_to_kill) = ([0i64; 2], 0.0f64, 0.0f64);
+
+    for fight in 0..fights {
+        let report = simulator.run(&home_units, &away_units, first_seed.wrapping_add(fight as u64))?;
+        match report.outcome {
+            BattleOutcome::HomeWin => {
+                result.home_wins += 1;
+                time_to_kill += report.duration_secs as f64;
+            }
+            BattleOutcome::AwayWin => result.away_wins += 1,
+            BattleOutcome::Draw => result.draws += 1,
+        }
+        for unit in &report.units {
+            damage[unit.stats.side.min(1)] += unit.stats.damage_dealt.total() as i64;
+        }
+        duration += report.duration_secs as f64;
+    }
+
+    if fights > 0 {
+        let n = fights as f64;
+        result.win_rate = ((result.home_wins as f64 + result.draws as f64 * 0.5) / n) as f32;
+        result.avg_damage_home = (damage[0] as f64 / n) as f32;
+        result.avg_damage_away = (damage[1] as f64 / n) as f32;
+        result.avg_duration_secs = (duration / n) as f32;
+    }
+    if result.home_wins > 0 {
+        result.avg_time_to_kill_secs = Some((time_to_kill / result.home_wins as f64) as f32);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+
+    #[test]
+    fn results_do_not_depend_on_the_thread_count() {
+        let config = ServerConfig::default();
+        let data_dir = config.data_dir.clone();
+        let simulator = BattleSimulator::load(Path::new(&data_dir), config).unwrap();
+        let compositions = Composition::single_units(&simulator.data().units, 1);
+        let run = |threads| {
+            let report = run_matrix(&simulator, &compositions, BalanceOptions { fights: 3, seed: 11, threads }).unwrap();
+            serde_json::to_string(&report).unwrap()
+        };
+        assert_eq!(run(1), run(4));
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("tanks"), "tanks");
+        assert_eq!(csv_field("tank, mage"), "\"tank, mage\"");
+        assert_eq!(csv_field("the \"wall\""), "\"the \"\"wall\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
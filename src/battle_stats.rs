@@ -0,0 +1,158 @@
+use serde::{Serialize, Deserialize};
+use specs::{System, Read, Write};
+use std::collections::HashMap;
+use uuid::Uuid;
+use crate::combat_log::{CombatEvent, CombatLog, DamageKind};
+use crate::replay::ReplayUnit;
+
+// 依傷害類型區分的傷害量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DamageBreakdown {
+    pub physical: i32,
+    pub magic: i32,
+    #[serde(rename = "true")]
+    pub true_damage: i32,
+}
+
+impl DamageBreakdown {
+    pub fn add(&mut self, kind: DamageKind, amount: i32) {
+        match kind {
+            DamageKind::Physical => self.physical += amount,
+            DamageKind::Magic => self.magic += amount,
+            DamageKind::True => self.true_damage += amount,
+        }
+    }
+
+    pub fn total(&self) -> i32 {
+        self.physical + self.magic + self.true_damage
+    }
+}
+
+// 一個棋子在一場戰鬥中的傷害統計（傷害表）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnitBattleStats {
+    pub id: Uuid,
+    pub unit_id: String,
+    pub side: usize,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub summoned: bool,
+    pub damage_dealt: DamageBreakdown, // 減免後實際造成的傷害
+    pub damage_taken: i32,
+    pub mitigated: i32, // 被防禦或魔抗減免掉的傷害
+    pub healing: i32,
+    pub casts: u32,
+    pub kills: u32,
+}
+
+impl UnitBattleStats {
+    pub fn new(id: Uuid, unit_id: &str, side: usize) -> Self {
+        UnitBattleStats {
+            id,
+            unit_id: unit_id.to_string(),
+            side,
+            summoned: false,
+            damage_dealt: DamageBreakdown::default(),
+            damage_taken: 0,
+            mitigated: 0,
+            healing: 0,
+            casts: 0,
+            kills: 0,
+        }
+    }
+}
+
+// 進行中戰鬥的傷害統計，依場地區分。伺服器與戰鬥模擬共用
+#[derive(Debug, Default)]
+pub struct BattleStats {
+    units: HashMap<Uuid, (usize, UnitBattleStats)>, // 棋子 id -> (場地, 統計)
+}
+
+impl BattleStats {
+    // 戰鬥開始時放入所有參戰棋子與所在場地
+    pub fn begin(&mut self, units: &[(usize, ReplayUnit)]) {
+        self.units = units
+            .iter()
+            .map(|(arena, unit)| (unit.id, (*arena, UnitBattleStats::new(unit.id, &unit.unit_id, unit.side))))
+            .collect();
+    }
+
+    pub fn record(&mut self, events: &[CombatEvent]) {
+        for event in events {
+            match event {
+                CombatEvent::Damage { source, target, amount, mitigated, kind, .. } => {
+                    // 中毒等自身效果的傷害不算造成的傷害
+                    if let Some(stats) = self.get_mut(*source).filter(|_| source != target) {
+                        stats.damage_dealt.add(*kind, *amount);
+                    }
+                    if let Some(stats) = self.get_mut(*target) {
+                        stats.damage_taken += amount;
+                        stats.mitigated += mitigated;
+                    }
+                }
+                CombatEvent::Heal { source, amount, .. } => {
+                    if let Some(stats) = self.get_mut(*source) {
+                        stats.healing += amount;
+                    }
+                }
+                CombatEvent::SkillCast { caster, .. } => {
+                    if let Some(stats) = self.get_mut(*caster) {
+                        stats.casts += 1;
+                    }
+                }
+                CombatEvent::Died { killer: Some(killer), .. } => {
+                    if let Some(stats) = self.get_mut(*killer) {
+                        stats.kills += 1;
+                    }
+                }
+                // 召喚物與施法者在同一場地、同一陣營
+                CombatEvent::Summoned { caster, unit, unit_id, .. } => {
+                    if let Some(&(arena, ref caster)) = self.units.get(caster) {
+                        let mut stats = UnitBattleStats::new(*unit, unit_id, caster.side);
+                        stats.summoned = true;
+                        self.units.insert(*unit, (arena, stats));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn get_mut(&mut self, id: Uuid) -> Option<&mut UnitBattleStats> {
+        self.units.get_mut(&id).map(|(_, stats)| stats)
+    }
+
+    pub fn unit(&self, id: Uuid) -> Option<&UnitBattleStats> {
+        self.units.get(&id).map(|(_, stats)| stats)
+    }
+
+    // 某個場地的統計，依陣營、造成的傷害由高到低排序
+    pub fn arena(&self, arena: usize) -> Vec<UnitBattleStats> {
+        let mut units: Vec<UnitBattleStats> = self
+            .units
+            .values()
+            .filter(|(a, _)| *a == arena)
+            .map(|(_, stats)| stats.clone())
+            .collect();
+        units.sort_by(|a, b| {
+            a.side
+                .cmp(&b.side)
+                .then(b.damage_dealt.total().cmp(&a.damage_dealt.total()))
+                .then(a.id.cmp(&b.id))
+        });
+        units
+    }
+}
+
+// 把每個 tick 的戰鬥紀錄累計到傷害統計
+pub struct BattleStatsSystem;
+
+impl<'a> System<'a> for BattleStatsSystem {
+    type SystemData = (
+        Read<'a, CombatLog>,
+        Write<'a, BattleStats>,
+    );
+
+    fn run(&mut self, (combat_log, mut battle_stats): Self::SystemData) {
+        battle_stats.record(&combat_log.events);
+    }
+}
@@ -0,0 +1,157 @@
+// 平衡測試工具：以固定種子大量模擬陣容之間的對戰，輸出勝率矩陣與平均傷害、擊殺時間
+//
+// cargo run --release --bin balance -- [--compositions comps.json] [--fights 1000] [--format csv|matrix|json]
+use ac_backend::balance::{self, BalanceOptions, Composition};
+use ac_backend::config::ServerConfig;
+use ac_backend::simulator::BattleSimulator;
+use std::path::PathBuf;
+use std::time::Instant;
+
+const USAGE: &str = "usage: balance [--data-dir DIR] [--config FILE] [--compositions FILE] [--level N] \
+[--fights N] [--seed N] [--threads N] [--tick-rate N] [--combat-time SECS] [--format csv|matrix|json] [--output FILE]";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Csv,
+    Matrix,
+    Json,
+}
+
+struct Args {
+    data_dir: PathBuf,
+    config: Option<String>,
+    compositions: Option<PathBuf>,
+    level: u32,
+    fights: u32,
+    seed: u64,
+    threads: usize,
+    tick_rate: Option<u32>,
+    combat_time: Option<f32>,
+    format: Format,
+    output: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut parsed = Args {
+            data_dir: PathBuf::from("data"),
+            config: None,
+            compositions: None,
+            level: 1,
+            fights: 1000,
+            seed: 0,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            tick_rate: None,
+            combat_time: None,
+            format: Format::Csv,
+            output: None,
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), value.to_string()),
+                None => {
+                    let value = iter.next().ok_or_else(|| format!("missing value for {}", arg))?;
+                    (arg.clone(), value.clone())
+                }
+            };
+            match flag.as_str() {
+                "--data-dir" => parsed.data_dir = PathBuf::from(value),
+                "--config" => parsed.config = Some(value),
+                "--compositions" => parsed.compositions = Some(PathBuf::from(value)),
+                "--level" => parsed.level = parse(&flag, &value)?,
+                "--fights" => parsed.fights = parse(&flag, &value)?,
+                "--seed" => parsed.seed = parse(&flag, &value)?,
+                "--threads" => parsed.threads = parse(&flag, &value)?,
+                "--tick-rate" => parsed.tick_rate = Some(parse(&flag, &value)?),
+                "--combat-time" => parsed.combat_time = Some(parse(&flag, &value)?),
+                "--format" => {
+                    parsed.format = match value.as_str() {
+                        "csv" => Format::Csv,
+                        "matrix" => Format::Matrix,
+                        "json" => Format::Json,
+                        _ => return Err(format!("invalid value for --format: {:?}", value)),
+                    }
+                }
+                "--output" => parsed.output = Some(PathBuf::from(value)),
+                _ => return Err(format!("unknown argument {}", flag)),
+            }
+        }
+        if parsed.fights == 0 || parsed.threads == 0 || parsed.level == 0 {
+            return Err("--fights, --threads and --level must be at least 1".to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {}: {:?}", key, value))
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    let args = Args::parse(&args).unwrap_or_else(|e| exit(&format!("{}\n{}", e, USAGE)));
+
+    // 戰鬥時間、tick 頻率與魔力規則沿用伺服器設定
+    let mut config = match &args.config {
+        Some(path) => ServerConfig::from_file(path).unwrap_or_else(|e| exit(&e.to_string())),
+        None => ServerConfig::default(),
+    };
+    if let Some(tick_rate) = args.tick_rate {
+        config.tick_rate = tick_rate;
+    }
+    if let Some(combat_time) = args.combat_time {
+        config.combat_time = combat_time;
+    }
+    if let Err(e) = config.validate() {
+        exit(&format!("設定錯誤: {}", e));
+    }
+
+    let simulator = BattleSimulator::load(&args.data_dir, config)
+        .unwrap_or_else(|e| exit(&format!("無法載入遊戲資料: {}", e)));
+    let compositions = match &args.compositions {
+        Some(path) => Composition::load_file(path).unwrap_or_else(|e| exit(&format!("無法載入陣容: {}", e))),
+        None => Composition::single_units(&simulator.data().units, args.level),
+    };
+    if compositions.is_empty() {
+        exit("沒有可以模擬的陣容");
+    }
+
+    let total = compositions.len() * compositions.len() * args.fights as usize;
+    eprintln!(
+        "模擬 {} 組陣容，共 {} 場戰鬥（{} 個執行緒）",
+        compositions.len(),
+        total,
+        args.threads
+    );
+    let started = Instant::now();
+    let options = BalanceOptions { fights: args.fights, seed: args.seed, threads: args.threads };
+    let report = balance::run_matrix(&simulator, &compositions, options)
+        .unwrap_or_else(|e| exit(&format!("模擬失敗: {}", e)));
+    let elapsed = started.elapsed().as_secs_f32();
+    eprintln!("完成，耗時 {:.2} 秒（每秒 {:.0} 場）", elapsed, total as f32 / elapsed.max(f32::EPSILON));
+
+    let text = match args.format {
+        Format::Csv => report.to_csv(),
+        Format::Matrix => report.matrix_csv(),
+        Format::Json => serde_json::to_string_pretty(&report).expect("report serializes"),
+    };
+    match &args.output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, text) {
+                exit(&format!("無法寫入 {}: {}", path.display(), e));
+            }
+        }
+        None => print!("{}", text),
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
@@ -0,0 +1,166 @@
+// 無網路的電腦對局：所有玩家都由電腦控制，不睡眠地從開局執行到遊戲結束，
+// 用來在大量種子上找出崩潰與無法結束的對局
+//
+// cargo run --release --bin bot_match -- [--seed 0] [--count 1000] [--max-rounds 100] [伺服器設定參數...]
+use ac_backend::config::ServerConfig;
+use ac_backend::headless::{BotMatch, MatchSummary};
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+const USAGE: &str = "usage: bot_match [--seed N] [--count N] [--threads N] [--max-rounds N] [--output FILE] \
+[server options such as --config FILE, --players N, --bots easy,hard, --tick-rate N ...]";
+
+struct Args {
+    seed: u64,
+    count: usize,
+    threads: usize,
+    max_rounds: u32,
+    output: Option<PathBuf>,
+    server_args: Vec<String>, // 其餘參數交給 ServerConfig
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut parsed = Args {
+            seed: 0,
+            count: 1,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            max_rounds: 100,
+            output: None,
+            server_args: Vec::new(),
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), value.to_string()),
+                None => {
+                    let value = iter.next().ok_or_else(|| format!("missing value for {}", arg))?;
+                    (arg.clone(), value.clone())
+                }
+            };
+            match flag.as_str() {
+                "--seed" => parsed.seed = parse(&flag, &value)?,
+                "--count" => parsed.count = parse(&flag, &value)?,
+                "--threads" => parsed.threads = parse(&flag, &value)?,
+                "--max-rounds" => parsed.max_rounds = parse(&flag, &value)?,
+                "--output" => parsed.output = Some(PathBuf::from(value)),
+                _ => parsed.server_args.extend([flag, value]),
+            }
+        }
+        if parsed.count == 0 || parsed.threads == 0 || parsed.max_rounds == 0 {
+            return Err("--count, --threads and --max-rounds must be at least 1".to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {}: {:?}", key, value))
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    let args = Args::parse(&args).unwrap_or_else(|e| exit(&format!("{}\n{}", e, USAGE)));
+    let env: Vec<(String, String)> = std::env::vars().collect();
+    let config = ServerConfig::load_from(&args.server_args, &env).unwrap_or_else(|e| exit(&format!("設定錯誤: {}", e)));
+    let data_dir = PathBuf::from(&config.data_dir);
+    let bot_match = BotMatch::load(&data_dir, config).unwrap_or_else(|e| exit(&format!("無法載入遊戲資料: {}", e)));
+
+    eprintln!("執行 {} 場電腦對局，種子 {}..{}（{} 個執行緒）", args.count, args.seed, args.seed + args.count as u64, args.threads);
+    let started = Instant::now();
+
+    // 每個執行緒輪流取下一個種子；結果依種子順序輸出，與執行緒數無關
+    let results: Mutex<Vec<Option<Result<MatchSummary, String>>>> = Mutex::new(vec![None; args.count]);
+    let next = AtomicUsize::new(0);
+    // 崩潰的對局會以錯誤回報，不需要預設的 panic 訊息
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    std::thread::scope(|scope| {
+        for _ in 0..args.threads.min(args.count) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= args.count {
+                    break;
+                }
+                let seed = args.seed + index as u64;
+                let result = match panic::catch_unwind(AssertUnwindSafe(|| bot_match.run(seed, args.max_rounds))) {
+                    Ok(Ok(summary)) => Ok(summary),
+                    Ok(Err(e)) => Err(e.to_string()),
+                    Err(panic) => Err(format!("seed {}: panicked: {}", seed, panic_message(&*panic))),
+                };
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    panic::set_hook(default_hook);
+    let elapsed = started.elapsed().as_secs_f32();
+
+    let results: Vec<Result<MatchSummary, String>> = results.into_inner().unwrap().into_iter().flatten().collect();
+    let mut lines = String::new();
+    let mut failures = Vec::new();
+    let mut wins: BTreeMap<String, usize> = BTreeMap::new();
+    let (mut rounds, mut combats, mut timed_out) = (0u64, 0usize, 0usize);
+    for result in &results {
+        match result {
+            Ok(summary) => {
+                lines.push_str(&serde_json::to_string(summary).expect("summary serializes"));
+                lines.push('\n');
+                rounds += summary.rounds as u64;
+                combats += summary.combats;
+                timed_out += summary.timed_out_combats;
+                if let Some(first) = summary.placements.first() {
+                    *wins.entry(first.profile.clone()).or_default() += 1;
+                }
+            }
+            Err(e) => failures.push(e.clone()),
+        }
+    }
+    match &args.output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, lines) {
+                exit(&format!("無法寫入 {}: {}", path.display(), e));
+            }
+        }
+        None => print!("{}", lines),
+    }
+
+    let finished = results.len() - failures.len();
+    eprintln!("完成 {} 場，失敗 {} 場，耗時 {:.2} 秒", finished, failures.len(), elapsed);
+    if finished > 0 {
+        eprintln!(
+            "平均 {:.1} 回合，{} 場戰鬥中 {} 場時間到未分勝負，各難度第一名次數 {:?}",
+            rounds as f32 / finished as f32,
+            combats,
+            timed_out,
+            wins
+        );
+    }
+    for failure in &failures {
+        eprintln!("  {}", failure);
+    }
+    if !failures.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown panic".to_string(),
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
@@ -0,0 +1,183 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use specs::{Component, VecStorage, System, Entities, ReadStorage, WriteStorage, Join, Read, ReadExpect, Write};
+use uuid::Uuid;
+use crate::{Chess, Owner, Position, Bench};
+use crate::carousel::Carousel;
+use crate::commands::{CommandQueue, PlayerCommand};
+use crate::config::ServerConfig;
+use crate::formation::{self, unit_value, FormationUnit};
+use crate::game_state::{SimTime, BENCH_SIZE};
+use crate::shop;
+use crate::turn::{Player, TurnState, TurnPhase};
+use crate::units::UnitDefinitions;
+
+// 電腦玩家的難度與策略
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BotProfile {
+    pub name: &'static str,
+    pub gold_reserve: i32,   // 購買棋子後至少保留的金幣（利息）
+    pub level_above: i32,    // 購買經驗後金幣仍不低於此值時才升級
+    pub reroll_above: i32,   // 刷新後金幣仍不低於此值時才刷新商店
+    pub mistake_chance: f64, // 每次行動有此機率什麼都不做
+    pub think_ticks: u64,    // 每隔幾個 tick 行動一次
+}
+
+impl BotProfile {
+    pub const NAMES: &'static [&'static str] = &["easy", "normal", "hard", "reroll"];
+
+    pub fn named(name: &str) -> Option<Self> {
+        let profile = match name {
+            // 只買眼前的棋子，從不存錢、升級或刷新
+            "easy" => BotProfile { name: "easy", gold_reserve: 0, level_above: i32::MAX, reroll_above: i32::MAX, mistake_chance: 0.3, think_ticks: 15 },
+            "normal" => BotProfile { name: "normal", gold_reserve: 10, level_above: 30, reroll_above: 50, mistake_chance: 0.05, think_ticks: 5 },
+            // 存滿利息，多出來的金幣用來升級
+            "hard" => BotProfile { name: "hard", gold_reserve: 50, level_above: 50, reroll_above: 70, mistake_chance: 0.0, think_ticks: 1 },
+            // 低等級大量刷新商店
+            "reroll" => BotProfile { name: "reroll", gold_reserve: 0, level_above: 60, reroll_above: 10, mistake_chance: 0.0, think_ticks: 1 },
+            _ => return None,
+        };
+        Some(profile)
+    }
+}
+
+// 由電腦控制的玩家，和真人玩家一樣透過 CommandQueue 下指令。
+// 決策使用自己的亂數，不影響模擬亂數，因此對局只由種子與記錄下來的指令決定
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct Bot {
+    pub profile: BotProfile,
+    pub next_action: u64, // 下一次可以行動的 tick
+    rng: StdRng,
+}
+
+impl Bot {
+    // 決策亂數由對局種子與玩家編號決定
+    pub fn new(profile: BotProfile, seed: u64, player_id: usize) -> Self {
+        let rng = StdRng::seed_from_u64(seed ^ (player_id as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        Bot { profile, next_action: 0, rng }
+    }
+}
+
+// 價值最低的棋子，價值相同時取 id 較小者
+fn weakest<'u>(units: &[&'u FormationUnit]) -> Option<&'u FormationUnit> {
+    units.iter().min_by_key(|u| (u.value, u.id)).copied()
+}
+
+// 依目前狀態決定電腦玩家的下一個指令，每次只做一件事
+fn decide(
+    profile: &BotProfile,
+    player: &Player,
+    units: &[FormationUnit],
+    unit_definitions: &UnitDefinitions,
+    config: &ServerConfig,
+) -> Option<PlayerCommand> {
+    let (board, bench): (Vec<&FormationUnit>, Vec<&FormationUnit>) =
+        units.iter().partition(|unit| unit.on_board());
+
+    // 1. 擺放：最有價值的棋子不在場上，或陣型不是坦克在前、遠程在後時自動排陣
+    if !formation::arrange(units, player.level as usize).is_empty() {
+        return Some(PlayerCommand::AutoPlace);
+    }
+
+    // 2. 購買：只買能讓陣容變強的棋子（買了就能上場，或比場上最弱的棋子更有價值）
+    let room = board.len() + bench.len() < player.level as usize;
+    let weakest_board = weakest(&board).map_or(0, |u| u.value);
+    let wanted = player
+        .shop
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, unit_id)| unit_definitions.get(unit_id).map(|definition| (slot, definition.cost)))
+        .filter(|&(_, cost)| room || unit_value(cost, 1) > weakest_board)
+        .max_by_key(|&(slot, cost)| (cost, std::cmp::Reverse(slot)));
+    if let Some((slot, cost)) = wanted {
+        // 補滿場上空位不受存款限制，替換較弱的棋子才需要保留存款
+        let left = player.gold - cost as i32;
+        if left >= 0 && (room || left >= profile.gold_reserve) {
+            if bench.len() < BENCH_SIZE {
+                return Some(PlayerCommand::Buy { slot });
+            }
+            // 備戰席已滿：賣掉比要買的棋子更弱的備戰棋子
+            if let Some(worst) = weakest(&bench) {
+                if worst.value < unit_value(cost, 1) {
+                    return Some(PlayerCommand::Sell { unit: worst.id });
+                }
+            }
+        }
+    }
+
+    // 3. 升級與刷新，只花超過門檻的金幣
+    if shop::experience_to_next(player).is_some() && player.gold - config.level_cost >= profile.level_above {
+        return Some(PlayerCommand::Level);
+    }
+    if player.gold - config.reroll_cost >= profile.reroll_above {
+        return Some(PlayerCommand::Reroll);
+    }
+    None
+}
+
+// 輪抽時選擇最有價值的棋子，有道具的優先
+fn decide_pick(carousel: &Carousel, unit_definitions: &UnitDefinitions) -> Option<PlayerCommand> {
+    carousel
+        .offers
+        .iter()
+        .enumerate()
+        .filter(|(_, offer)| offer.picked_by.is_none())
+        .max_by_key(|(index, offer)| {
+            let cost = unit_definitions.get(&offer.unit_id).map_or(0, |d| d.cost);
+            (cost, offer.item.is_some(), std::cmp::Reverse(*index))
+        })
+        .map(|(offer, _)| PlayerCommand::Pick { offer })
+}
+
+// 每個 tick 替電腦玩家決定動作並放入指令佇列
+pub struct BotSystem;
+
+impl<'a> System<'a> for BotSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Bot>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Chess>,
+        ReadStorage<'a, Owner>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Bench>,
+        ReadStorage<'a, TurnState>,
+        Read<'a, Carousel>,
+        Read<'a, UnitDefinitions>,
+        ReadExpect<'a, ServerConfig>,
+        Read<'a, SimTime>,
+        Write<'a, CommandQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut bots, players, chess, owners, positions, benches, turn_states, carousel, unit_definitions, config, sim_time, mut queue) = data;
+        let phase = match (&turn_states).join().next() {
+            Some(turn_state) => turn_state.current_phase,
+            None => return,
+        };
+
+        for (bot, player) in (&mut bots, &players).join() {
+            if player.health <= 0 || sim_time.tick < bot.next_action {
+                continue;
+            }
+            let command = match phase {
+                TurnPhase::Carousel if carousel.picking() == Some(player.id) => decide_pick(&carousel, &unit_definitions),
+                TurnPhase::Preparation => {
+                    let units = formation::owned_units(player.id, &entities, &chess, &owners, &positions, &benches, &unit_definitions);
+                    decide(&bot.profile, player, &units, &unit_definitions, &config)
+                }
+                _ => None,
+            };
+            let command = match command {
+                Some(command) => command,
+                None => continue,
+            };
+            bot.next_action = sim_time.tick + bot.profile.think_ticks;
+            if bot.profile.mistake_chance > 0.0 && bot.rng.gen_bool(bot.profile.mistake_chance) {
+                continue;
+            }
+            queue.push(player.id, command);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use serde::Serialize;
+
+// 輪抽中的一個選項：一個 1 星棋子，可能帶著一個道具
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CarouselOffer {
+    pub unit_id: String,
+    pub item: Option<String>,
+    pub picked_by: Option<usize>,
+}
+
+// 共享輪抽：血量最低的玩家先選，每人有 pick_time 秒，逾時自動分配
+#[derive(Debug, Clone, Default)]
+pub struct Carousel {
+    pub offers: Vec<CarouselOffer>,
+    pub order: Vec<usize>,      // 選擇順序（玩家 id）
+    pub turn: usize,            // 目前輪到 order 中的第幾位
+    pub pick_remaining: f32,    // 目前玩家剩餘的選擇時間
+    granted: usize,             // 已發放給玩家的選擇數
+}
+
+// 送給客戶端的輪抽狀態
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CarouselView {
+    pub offers: Vec<CarouselOffer>,
+    pub order: Vec<usize>,
+    pub picking: Option<usize>,
+    pub pick_remaining_secs: u32,
+}
+
+impl Carousel {
+    pub fn new(offers: Vec<CarouselOffer>, order: Vec<usize>, pick_time: f32) -> Self {
+        Carousel {
+            offers,
+            order,
+            turn: 0,
+            pick_remaining: pick_time,
+            granted: 0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.offers.is_empty()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.turn >= self.order.len()
+    }
+
+    // 目前輪到的玩家
+    pub fn picking(&self) -> Option<usize> {
+        self.order.get(self.turn).copied()
+    }
+
+    pub fn pick(&mut self, player_id: usize, offer: usize, pick_time: f32) -> Result<(), String> {
+        if self.picking() != Some(player_id) {
+            return Err("it is not your turn to pick".to_string());
+        }
+        match self.offers.get_mut(offer) {
+            None => Err(format!("no carousel offer {}", offer)),
+            Some(chosen) if chosen.picked_by.is_some() => Err(format!("carousel offer {} was already taken", offer)),
+            Some(chosen) => {
+                chosen.picked_by = Some(player_id);
+                self.turn += 1;
+                self.pick_remaining = pick_time;
+                Ok(())
+            }
+        }
+    }
+
+    // 目前玩家逾時，自動分配第一個還沒被選走的選項，回傳 (玩家 id, 選項)
+    pub fn auto_pick(&mut self, pick_time: f32) -> Option<(usize, usize)> {
+        let player_id = self.picking()?;
+        match self.offers.iter().position(|o| o.picked_by.is_none()) {
+            Some(offer) => {
+                let _ = self.pick(player_id, offer, pick_time);
+                Some((player_id, offer))
+            }
+            None => {
+                self.turn += 1;
+                None
+            }
+        }
+    }
+
+    // 取出尚未發放的選擇：(玩家 id, 選項)
+    pub fn take_picks(&mut self) -> Vec<(usize, CarouselOffer)> {
+        let picks: Vec<(usize, CarouselOffer)> = self.order[self.granted..self.turn]
+            .iter()
+            .filter_map(|&player_id| {
+                self.offers
+                    .iter()
+                    .find(|o| o.picked_by == Some(player_id))
+                    .map(|o| (player_id, o.clone()))
+            })
+            .collect();
+        self.granted = self.turn;
+        picks
+    }
+
+    pub fn view(&self) -> CarouselView {
+        CarouselView {
+            offers: self.offers.clone(),
+            order: self.order.clone(),
+            picking: self.picking(),
+            pick_remaining_secs: self.pick_remaining.max(0.0).ceil() as u32,
+        }
+    }
+}
@@ -1,110 +1,383 @@
-use specs::{System, ReadStorage, WriteStorage, Join, Entities, Entity};
-use crate::{Position, Chess, CombatStats, StatusEffects, Effect, EffectType, SkillType};
-use crate::skills::{WhirlwindSlash, Fireball, MultiShot, ShieldBash, SkillExecutor};
+use specs::{Component, DenseVecStorage, System, ReadStorage, WriteStorage, Join, Entities, Entity, Read, ReadExpect, Write, LazyUpdate};
+use uuid::Uuid;
+use crate::{Position, Chess, CombatStats, StatusEffects, Effect, EffectType};
+use crate::combat_log::{CombatEvent, CombatLog, DamageKind, VampKind};
+use crate::config::ServerConfig;
+use crate::game_state::{SimTime, BOARD_WIDTH, BOARD_HEIGHT};
+use crate::skills::{SkillContext, SkillRegistry, HitRoll, mitigate, distance, roll_hit, vamp};
+use crate::stats::HitStats;
+use crate::rounds::Team;
+use crate::rng::SimRng;
+use crate::units::UnitDefinitions;
+use crate::targeting::{self, Candidate, CurrentTarget};
+
+// 正在施法前搖的棋子：不會普攻，也不會獲得魔力（魔力鎖定）
+#[derive(Component, Debug, Clone)]
+#[storage(DenseVecStorage)]
+pub struct Casting {
+    pub remaining: f32, // 剩餘的前搖時間（秒）
+}
+
+// 魔力獲得規則，來自 ServerConfig
+#[derive(Debug, Clone, Copy)]
+pub struct ManaRules {
+    pub per_attack: i32,
+    pub per_damage_taken: f32,
+    pub max_per_hit: i32,
+}
+
+impl ManaRules {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        ManaRules {
+            per_attack: config.mana_per_attack,
+            per_damage_taken: config.mana_per_damage_taken,
+            max_per_hit: config.max_mana_per_hit,
+        }
+    }
+
+    // 受到 amount 點減免前傷害時獲得的魔力
+    pub fn from_damage_taken(&self, amount: i32) -> i32 {
+        ((amount.max(0) as f32 * self.per_damage_taken) as i32).min(self.max_per_hit)
+    }
+}
+
+// 增加魔力，施法中的棋子魔力鎖定
+pub fn gain_mana(stats: &mut CombatStats, casting: bool, amount: i32) {
+    if !casting {
+        stats.mana = (stats.mana + amount).min(stats.max_mana);
+    }
+}
+
+pub fn is_stunned(effects: Option<&StatusEffects>) -> bool {
+    effects.map_or(false, |effects| {
+        effects.effects.iter().any(|e| e.effect_type == EffectType::Stun && e.duration > 0.0)
+    })
+}
+
+// 治療或中毒效果生效一次，來源記為棋子自己
+fn effect_pulse(entity: Entity, effect: &Effect, stats: &mut CombatStats, unit_id: &impl Fn(Entity) -> Uuid, events: &mut Vec<CombatEvent>) {
+    let unit = unit_id(entity);
+    let amount = effect.magnitude.round() as i32;
+    match effect.effect_type {
+        EffectType::Heal => {
+            let healed = amount.max(0).min(stats.max_hp - stats.hp);
+            stats.hp += healed;
+            events.push(CombatEvent::Heal { source: unit, target: unit, amount: healed, vamp: None });
+        }
+        EffectType::Poison => {
+            let dealt = amount.max(0);
+            stats.hp -= dealt;
+            events.push(CombatEvent::Damage { source: unit, target: unit, amount: dealt, mitigated: 0, kind: DamageKind::True, skill: None, crit: false });
+            if stats.hp <= 0 {
+                events.push(CombatEvent::Died { unit, killer: None });
+            }
+        }
+        _ => {}
+    }
+}
 
 pub struct CombatSystem;
 
 impl<'a> System<'a> for CombatSystem {
     type SystemData = (
         Entities<'a>,
-        ReadStorage<'a, Position>,
+        WriteStorage<'a, Position>,
         ReadStorage<'a, Chess>,
+        ReadStorage<'a, Team>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, Casting>,
+        WriteStorage<'a, CurrentTarget>,
+        ReadExpect<'a, SkillRegistry>,
+        ReadExpect<'a, ServerConfig>,
+        Read<'a, SimTime>,
+        Read<'a, UnitDefinitions>,
+        Read<'a, LazyUpdate>,
+        Write<'a, SimRng>,
+        Write<'a, CombatLog>,
     );
 
-    fn run(&mut self, (entities, positions, chess, mut combat_stats, mut status_effects): Self::SystemData) {
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut positions, chess, teams, mut combat_stats, mut status_effects, mut castings, mut current_targets, skills, config, sim_time, unit_definitions, lazy, mut rng, mut combat_log) = data;
+
+        // 上一個 tick 的紀錄已經送出，開始新的紀錄
+        combat_log.tick = sim_time.tick;
+        combat_log.events.clear();
+        let events = &mut combat_log.events;
+
+        let delta = sim_time.delta;
+        let mana_rules = ManaRules::from_config(&config);
+        let unit_id = |entity: Entity| chess.get(entity).map(|c| c.id).unwrap_or_default();
+
         // 儲存所有需要處理的攻擊和技能
         let mut attacks: Vec<(Entity, Entity)> = Vec::new();
-        let mut skill_casts: Vec<(Entity, SkillType, i32)> = Vec::new();
+        let mut skill_casts: Vec<Entity> = Vec::new();
+        let mut cast_starts: Vec<Entity> = Vec::new();
+        let mut interrupted: Vec<Entity> = Vec::new();
+        let mut movers: Vec<Entity> = Vec::new();
+
+        // 記下本 tick 被暈眩的棋子後，對戰中棋子的狀態效果依模擬時間倒數，到期後移除。
+        // 治療與中毒在剩餘時間每跨過一個整數秒時生效一次
+        let stunned: Vec<Entity> = (&entities, &status_effects, &teams)
+            .join()
+            .filter(|(_, effects, _)| is_stunned(Some(effects)))
+            .map(|(entity, _, _)| entity)
+            .collect();
+        for (entity, effects, stats, _) in (&entities, &mut status_effects, &mut combat_stats, &teams).join() {
+            let mut expired = 0;
+            for (index, effect) in effects.effects.iter_mut().enumerate() {
+                let pulse = (effect.duration - delta).ceil() < effect.duration.ceil();
+                if pulse && stats.hp > 0 {
+                    effect_pulse(entity, effect, stats, &unit_id, events);
+                }
+                effect.duration -= delta;
+                if effect.duration <= 0.0 {
+                    let (effect_type, index) = (effect.effect_type.clone(), index - expired);
+                    events.push(CombatEvent::EffectExpired { unit: unit_id(entity), effect_type, index });
+                    expired += 1;
+                }
+            }
+            effects.effects.retain(|effect| effect.duration > 0.0);
+        }
 
         // 第一階段：收集資訊
         {
             let combat_stats_ref = &combat_stats;
-            let mut potential_actions: Vec<(Entity, bool, i32, SkillType)> = Vec::new();
 
-            for (e1, pos1, stats1) in (&entities, &positions, combat_stats_ref).join() {
-                if stats1.mana >= stats1.max_mana && stats1.skill.current_cooldown == 0 {
-                    potential_actions.push((e1, true, stats1.skill.damage, stats1.skill.skill_type.clone()));
+            // 只有正在對戰且存活的棋子會行動，並只攻擊同一場地的敵方棋子
+            for (e1, pos1, team1, stats1) in (&entities, &positions, &teams, combat_stats_ref).join() {
+                if stats1.hp <= 0 {
+                    continue;
+                }
+                // 暈眩時不能行動，前搖中的技能會被打斷
+                if stunned.contains(&e1) {
+                    if castings.contains(e1) {
+                        interrupted.push(e1);
+                    }
+                    continue;
+                }
+                if let Some(casting) = castings.get(e1) {
+                    if casting.remaining <= delta {
+                        skill_casts.push(e1);
+                    }
+                    continue;
+                }
+                let skill = &stats1.skill;
+                let mana_ready = !skill.requires_mana || stats1.mana >= stats1.max_mana;
+                if mana_ready && skill.current_cooldown <= 0.0 {
+                    cast_starts.push(e1);
                 } else {
-                    for (e2, pos2, _) in (&entities, &positions, combat_stats_ref).join() {
-                        if e1 != e2 && calculate_distance(pos1, pos2) <= stats1.attack_range {
-                            attacks.push((e1, e2));
-                            break;
+                    // 依棋子的目標策略從攻擊範圍內的敵人中選擇，範圍內沒有敵人時往敵人移動
+                    let candidates: Vec<Candidate> = (&entities, &positions, &teams, combat_stats_ref)
+                        .join()
+                        .filter(|(_, _, team2, stats2)| team1.is_enemy(team2) && stats2.hp > 0)
+                        .map(|(e2, pos2, _, stats2)| Candidate { entity: e2, distance: distance(pos1, pos2), hp: stats2.hp, attack: stats2.attack })
+                        .filter(|candidate| candidate.distance <= stats1.attack_range)
+                        .collect();
+                    if candidates.is_empty() {
+                        if stats1.move_cooldown <= 0.0 {
+                            movers.push(e1);
                         }
+                        continue;
+                    }
+                    if stats1.attack_cooldown > 0.0 {
+                        continue;
+                    }
+                    let current = current_targets.get(e1).map(|current| current.target);
+                    if let Some(&target) = targeting::rank(stats1.targeting, candidates, current, &mut rng).first() {
+                        attacks.push((e1, target));
                     }
                 }
             }
+        }
 
-            // 更新狀態
-            for (entity, is_skill, damage, skill_type) in potential_actions {
-                if let Some(mut stats) = combat_stats.get_mut(entity) {
-                    if is_skill {
-                        skill_casts.push((entity, skill_type, damage));
-                        stats.mana = 0;
-                        stats.skill.current_cooldown = stats.skill.cooldown;
-                    }
-                }
+        // 更新施法狀態：開始前搖時消耗魔力並進入冷卻
+        for entity in interrupted {
+            castings.remove(entity);
+            if let Some(stats) = combat_stats.get(entity) {
+                events.push(CombatEvent::CastInterrupted { caster: unit_id(entity), skill: stats.skill.id.clone() });
+            }
+        }
+        for (casting, _) in (&mut castings, &entities).join() {
+            casting.remaining -= delta;
+        }
+        for &entity in &skill_casts {
+            castings.remove(entity);
+        }
+        for entity in cast_starts {
+            let stats = match combat_stats.get_mut(entity) {
+                Some(stats) => stats,
+                None => continue,
+            };
+            if stats.skill.requires_mana {
+                stats.mana = 0;
             }
+            stats.skill.current_cooldown = stats.skill.cooldown;
+            if stats.skill.cast_time <= 0.0 {
+                skill_casts.push(entity);
+                continue;
+            }
+            events.push(CombatEvent::CastStarted {
+                caster: unit_id(entity),
+                skill: stats.skill.id.clone(),
+                cast_time: stats.skill.cast_time,
+            });
+            let _ = castings.insert(entity, Casting { remaining: stats.skill.cast_time });
         }
 
-        // 第二階段：處理攻擊
+        // 第二階段：處理攻擊，下次普攻的時間依攻擊速度計算
+        for &(attacker, target) in &attacks {
+            let _ = current_targets.insert(attacker, CurrentTarget { target });
+            if let Some(stats) = combat_stats.get_mut(attacker) {
+                stats.attack_cooldown += 1.0 / stats.attack_speed;
+            }
+        }
         for (attacker, target) in attacks {
-            if let Some(attacker_stats) = combat_stats.get(attacker) {
-                let attack_damage = calculate_damage(&attacker_stats, combat_stats.get(target).unwrap());
-                if let Some(mut target_stats) = combat_stats.get_mut(target) {
-                    target_stats.hp -= attack_damage;
-                    target_stats.mana = (target_stats.mana + 1).min(target_stats.max_mana);
+            let attacker_hit = match combat_stats.get(attacker) {
+                Some(attacker_stats) => HitStats::of(attacker_stats, status_effects.get(attacker)),
+                None => continue,
+            };
+            let target_hit = match combat_stats.get(target) {
+                Some(target_stats) if target_stats.hp > 0 => HitStats::of(target_stats, status_effects.get(target)),
+                _ => continue,
+            };
+            // 閃避時目標不受傷害也不獲得魔力，攻擊者仍獲得普攻的魔力
+            let mut dealt = 0;
+            match roll_hit(attacker_hit.attack, &attacker_hit, &target_hit, true, &mut rng) {
+                HitRoll::Dodged => events.push(CombatEvent::Dodged { source: unit_id(attacker), target: unit_id(target) }),
+                HitRoll::Hit { amount, crit } => {
+                    let target_casting = castings.contains(target);
+                    dealt = mitigate(amount, DamageKind::Physical, &target_hit);
+                    let target_stats = combat_stats.get_mut(target).unwrap();
+                    target_stats.hp -= dealt;
+                    gain_mana(target_stats, target_casting, mana_rules.from_damage_taken(amount));
+                    events.push(CombatEvent::Damage {
+                        source: unit_id(attacker),
+                        target: unit_id(target),
+                        amount: dealt,
+                        mitigated: amount.max(0) - dealt,
+                        kind: DamageKind::Physical,
+                        skill: None,
+                        crit,
+                    });
+                    if target_stats.hp <= 0 {
+                        events.push(CombatEvent::Died { unit: unit_id(target), killer: Some(unit_id(attacker)) });
+                    }
                 }
-                if let Some(mut attacker_stats) = combat_stats.get_mut(attacker) {
-                    attacker_stats.mana = (attacker_stats.mana + 5).min(attacker_stats.max_mana);
+            }
+            let attacker_casting = castings.contains(attacker);
+            if let Some(attacker_stats) = combat_stats.get_mut(attacker) {
+                gain_mana(attacker_stats, attacker_casting, mana_rules.per_attack);
+                let healed = vamp(attacker_stats, dealt, attacker_hit.lifesteal);
+                if healed > 0 {
+                    let source = unit_id(attacker);
+                    events.push(CombatEvent::Heal { source, target: source, amount: healed, vamp: Some(VampKind::Lifesteal) });
                 }
             }
         }
 
         // 第三階段：處理技能
-        for (caster, skill_type, damage) in skill_casts {
-            let skill: Box<dyn SkillExecutor> = match skill_type {
-                SkillType::WhirlwindSlash => Box::new(WhirlwindSlash),
-                SkillType::Fireball => Box::new(Fireball),
-                SkillType::MultiShot => Box::new(MultiShot),
-                SkillType::ShieldBash => Box::new(ShieldBash),
+        for caster in skill_casts {
+            let skill = match combat_stats.get(caster) {
+                Some(stats) if stats.hp > 0 => stats.skill.clone(),
+                _ => continue,
+            };
+            let executor = match skills.get(&skill.id) {
+                Some(executor) => executor,
+                None => {
+                    log::warn!("Unknown skill id: {}", skill.id);
+                    continue;
+                }
             };
+            events.push(CombatEvent::SkillCast { caster: unit_id(caster), skill: skill.id.clone() });
 
-            skill.execute(
+            let mut ctx = SkillContext {
                 caster,
-                damage,
-                &entities,
-                &positions,
-                &mut combat_stats,
-                &mut status_effects,
-            );
+                skill_id: &skill.id,
+                damage: skill.damage,
+                range: skill.range,
+                duration: skill.duration,
+                entities: &entities,
+                chess: &chess,
+                teams: &teams,
+                positions: &mut positions,
+                combat_stats: &mut combat_stats,
+                status_effects: &mut status_effects,
+                castings: &castings,
+                targeting: skill.targeting,
+                current_target: current_targets.get(caster).map(|current| current.target),
+                mana_rules,
+                unit_definitions: &*unit_definitions,
+                lazy: &*lazy,
+                rng: &mut *rng,
+                events: &mut *events,
+            };
+            executor.execute(&mut ctx);
         }
 
-        // 更新技能冷卻
-        for stats in (&mut combat_stats).join() {
-            if stats.skill.current_cooldown > 0 {
-                stats.skill.current_cooldown -= 1;
+        // 第四階段：範圍內沒有敵人的棋子依序往最近的敵人移動一格
+        for mover in movers {
+            if let Some((from, to)) = step_toward_enemy(mover, &entities, &positions, &teams, &combat_stats) {
+                if let Some(position) = positions.get_mut(mover) {
+                    *position = Position { x: to.0, y: to.1 };
+                }
+                if let Some(stats) = combat_stats.get_mut(mover) {
+                    stats.move_cooldown += config.move_time;
+                }
+                events.push(CombatEvent::Moved { unit: unit_id(mover), from, to });
             }
         }
+
+        // 依模擬時間更新對戰中棋子的技能冷卻與普攻間隔。普攻間隔保留最多一個 tick 的超出時間，
+        // 讓每秒的普攻次數不受 tick rate 影響
+        for (stats, _) in (&mut combat_stats, &teams).join() {
+            stats.skill.current_cooldown = (stats.skill.current_cooldown - delta).max(0.0);
+            stats.attack_cooldown = (stats.attack_cooldown - delta).max(-delta);
+            stats.move_cooldown = (stats.move_cooldown - delta).max(-delta);
+        }
     }
 }
 
-// 計算傷害
-fn calculate_damage(attacker: &CombatStats, defender: &CombatStats) -> i32 {
-    let base_damage = attacker.attack;
-    let damage_reduction = defender.defense as f32 / 100.0;
-    (base_damage as f32 * (1.0 - damage_reduction)) as i32
-}
+// 棋子往最近的存活敵人（距離相同時 entity id 小的優先）移動一格後的位置。只走到同一場地內
+// 沒有存活棋子的相鄰格（含斜向），選擇離該敵人最近的一格，無法更靠近時不移動
+fn step_toward_enemy(
+    mover: Entity,
+    entities: &Entities,
+    positions: &WriteStorage<Position>,
+    teams: &ReadStorage<Team>,
+    combat_stats: &WriteStorage<CombatStats>,
+) -> Option<((i32, i32), (i32, i32))> {
+    let (from, team) = (positions.get(mover)?, teams.get(mover)?);
+    let living: Vec<(Entity, &Position, &Team)> = (entities, positions, teams, combat_stats)
+        .join()
+        .filter(|(entity, _, other, stats)| *entity != mover && other.arena == team.arena && stats.hp > 0)
+        .map(|(entity, position, other, _)| (entity, position, other))
+        .collect();
+    let (_, enemy, _) = living
+        .iter()
+        .filter(|(_, _, other)| team.is_enemy(other))
+        .min_by(|a, b| distance(from, a.1).total_cmp(&distance(from, b.1)).then(a.0.id().cmp(&b.0.id())))?;
 
-// 計算距離
-fn calculate_distance(pos1: &Position, pos2: &Position) -> f32 {
-    let dx = pos1.x - pos2.x;
-    let dy = pos1.y - pos2.y;
-    ((dx * dx + dy * dy) as f32).sqrt()
+    // 雙方棋盤合起來的戰鬥場地，見 game_state::arena_position
+    let mut best: Option<(f32, Position)> = None;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let cell = Position { x: from.x + dx, y: from.y + dy };
+            let inside = (0..BOARD_WIDTH).contains(&cell.x) && (0..2 * BOARD_HEIGHT).contains(&cell.y);
+            if (dx, dy) == (0, 0) || !inside || living.iter().any(|(_, p, _)| p.x == cell.x && p.y == cell.y) {
+                continue;
+            }
+            let d = distance(&cell, enemy);
+            if best.as_ref().map_or(true, |(best_d, _)| d < *best_d) {
+                best = Some((d, cell));
+            }
+        }
+    }
+    let (d, to) = best?;
+    (d < distance(from, enemy)).then(|| ((from.x, from.y), (to.x, to.y)))
 }
 
 // 判斷是否在攻擊範圍內
 pub fn is_in_range(attacker_pos: &Position, target_pos: &Position, range: f32) -> bool {
-    calculate_distance(attacker_pos, target_pos) <= range
-} 
\ No newline at end of file
+    distance(attacker_pos, target_pos) <= range
+}
@@ -0,0 +1,102 @@
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+use crate::{Effect, EffectType};
+
+// 傷害類型：物理傷害受防禦減免，魔法傷害受魔抗減免，真實傷害不受減免
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DamageKind {
+    Physical,
+    Magic,
+    True,
+}
+
+// 吸血的來源：普攻為 lifesteal，技能為 spell_vamp
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VampKind {
+    Lifesteal,
+    SpellVamp,
+}
+
+// 戰鬥紀錄中的一筆事件，棋子以 Chess::id 表示
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CombatEvent {
+    CastStarted { caster: Uuid, skill: String, cast_time: f32 }, // 開始施法前搖
+    CastInterrupted { caster: Uuid, skill: String },             // 前搖期間被暈眩打斷
+    SkillCast { caster: Uuid, skill: String },
+    Damage {
+        source: Uuid,
+        target: Uuid,
+        amount: i32,    // 實際扣除的生命值
+        mitigated: i32, // 被防禦或魔抗減免的數值
+        kind: DamageKind,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        skill: Option<String>, // 普通攻擊時為 None
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        crit: bool,
+    },
+    Dodged { source: Uuid, target: Uuid }, // 普攻被閃避，沒有造成傷害
+    Heal {
+        source: Uuid,
+        target: Uuid,
+        amount: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        vamp: Option<VampKind>, // 吸血造成的治療，技能治療時為 None
+    },
+    EffectApplied { source: Uuid, target: Uuid, effect: Effect },
+    // index 為到期的效果在棋子效果清單中的位置（已扣除同一 tick 先前移除的效果），
+    // 同類型的效果重疊時用來分辨是哪一個到期
+    EffectExpired { unit: Uuid, effect_type: EffectType, index: usize },
+    Moved { unit: Uuid, from: (i32, i32), to: (i32, i32) },
+    Summoned { caster: Uuid, unit: Uuid, unit_id: String, position: (i32, i32), hp: i32 },
+    Died { unit: Uuid, killer: Option<Uuid> },
+    Custom { unit: Uuid, message: String }, // 技能自訂的紀錄
+}
+
+impl CombatEvent {
+    // 事件所屬戰鬥中的棋子，用來判斷事件發生在哪個場地
+    pub fn unit(&self) -> Uuid {
+        match self {
+            CombatEvent::CastStarted { caster, .. }
+            | CombatEvent::CastInterrupted { caster, .. }
+            | CombatEvent::SkillCast { caster, .. }
+            | CombatEvent::Summoned { caster, .. } => *caster,
+            CombatEvent::Damage { target, .. }
+            | CombatEvent::Dodged { target, .. }
+            | CombatEvent::Heal { target, .. }
+            | CombatEvent::EffectApplied { target, .. } => *target,
+            CombatEvent::EffectExpired { unit, .. }
+            | CombatEvent::Moved { unit, .. }
+            | CombatEvent::Died { unit, .. }
+            | CombatEvent::Custom { unit, .. } => *unit,
+        }
+    }
+}
+
+// 本 tick 產生的戰鬥事件，由 CombatSystem 在每個 tick 開始時清空，
+// ViewSystem 與 SpectatorSystem 送給客戶端
+#[derive(Debug, Clone, Default)]
+pub struct CombatLog {
+    pub tick: u64,
+    pub events: Vec<CombatEvent>,
+}
+
+// 送往客戶端的戰鬥紀錄訊息，seq 與快照、差異共用同一個遞增序號
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename = "combat_log")]
+pub struct CombatLogMessage<'a> {
+    pub seq: u64,
+    pub tick: u64,
+    pub events: &'a [CombatEvent],
+}
+
+impl CombatLog {
+    pub fn to_message(&self, seq: u64) -> Option<String> {
+        if self.events.is_empty() {
+            return None;
+        }
+        serde_json::to_string(&CombatLogMessage { seq, tick: self.tick, events: &self.events }).ok()
+    }
+}
@@ -0,0 +1,416 @@
+use specs::{System, Entities, Entity, ReadStorage, WriteStorage, Join, Read, ReadExpect, Write, LazyUpdate, Builder};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+use std::collections::HashMap;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use crate::{Chess, Owner, Position, Bench, StatusEffects, ChannelMessage, WebsocketChannel};
+use crate::turn::{Player, TurnState, TurnPhase};
+use crate::units::UnitDefinitions;
+use crate::items::{Equipment, ItemDefinitions};
+use crate::view::ViewState;
+use crate::carousel::Carousel;
+use crate::config::ServerConfig;
+use crate::game_state::{SimTime, BENCH_SIZE, BOARD_WIDTH, BOARD_HEIGHT};
+use crate::rng::SimRng;
+use crate::shop;
+use crate::replay::ReplayRecorder;
+use crate::rounds::MatchHistory;
+use crate::formation::{self, place};
+
+// 客戶端送來的指令，格式為 {"type": "...", ...}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlayerCommand {
+    // 客戶端發現序號不連續時要求重新送出完整快照
+    Resync,
+    // 把道具欄第 item 個道具裝備到棋子上
+    Equip { item: usize, unit: Uuid },
+    // 賣出棋子，身上的道具回到道具欄
+    Sell { unit: Uuid },
+    // 輪抽時選擇第 offer 個選項
+    Pick { offer: usize },
+    // 要求自己某回合（未指定時為最近一回合）的戰鬥重播
+    Replay {
+        #[serde(default)]
+        round: Option<u32>,
+    },
+    // 要求自己本場遊戲每回合的對戰結果與傷害統計
+    History,
+    // 購買商店第 slot 個棋子，放到備戰席
+    Buy { slot: usize },
+    // 花費金幣刷新商店
+    Reroll,
+    // 花費金幣購買經驗
+    Level,
+    // 把棋子移到棋盤或備戰席的位置，目標位置有自己的棋子時互換
+    Move { unit: Uuid, to: Placement },
+    // 自動排陣：價值最高的棋子上場，坦克在前、遠程在後
+    AutoPlace,
+}
+
+// 棋子的擺放位置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Placement {
+    Board { x: i32, y: i32 },
+    Bench { slot: usize },
+}
+
+// 等待執行的指令，依收到的順序執行
+#[derive(Debug, Default)]
+pub struct CommandQueue {
+    pending: Vec<(usize, PlayerCommand)>,
+}
+
+impl CommandQueue {
+    pub fn push(&mut self, player_id: usize, command: PlayerCommand) {
+        self.pending.push((player_id, command));
+    }
+}
+
+// 會影響模擬結果的玩家輸入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "input", rename_all = "snake_case")]
+pub enum PlayerInput {
+    Command { command: PlayerCommand },
+    Connected,    // 斷線的玩家在輪抽時會被自動分配
+    Disconnected,
+    Abandoned,    // 斷線超過寬限時間，下一次配對前被淘汰
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputRecord {
+    pub tick: u64,
+    pub player_id: usize,
+    pub input: PlayerInput,
+}
+
+// 從某個 tick 起使用的遊戲資料，開局與每次套用熱更新時各記錄一筆
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataRecord {
+    pub tick: u64,
+    pub fingerprint: String,
+}
+
+// 依 tick 與執行順序記錄的玩家輸入；相同的種子、相同的遊戲資料加上這份紀錄即可重現整場對局，
+// 重現時以 InputReplay 把紀錄放回指令佇列
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputLog {
+    pub seed: u64,
+    #[serde(default)]
+    pub data: Vec<DataRecord>,
+    pub records: Vec<InputRecord>,
+}
+
+impl InputLog {
+    pub fn new(seed: u64) -> Self {
+        InputLog { seed, data: Vec::new(), records: Vec::new() }
+    }
+
+    pub fn record_data(&mut self, tick: u64, fingerprint: String) {
+        self.data.push(DataRecord { tick, fingerprint });
+    }
+
+    pub fn record(&mut self, tick: u64, player_id: usize, input: PlayerInput) {
+        self.records.push(InputRecord { tick, player_id, input });
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self).map_err(std::io::Error::from)
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        serde_json::from_reader(BufReader::new(file)).map_err(std::io::Error::from)
+    }
+}
+
+// 重播中的輸入紀錄，由 InputReplaySystem 在紀錄的 tick 送出
+#[derive(Debug, Default)]
+pub struct InputReplay {
+    records: Vec<InputRecord>,
+    next: usize,
+}
+
+impl InputReplay {
+    pub fn new(log: &InputLog) -> Self {
+        InputReplay { records: log.records.clone(), next: 0 }
+    }
+}
+
+// 取代 PlayersSystem 與 BotSystem 的輸入來源：在紀錄的 tick 依原本的順序把指令放回 CommandQueue，
+// 並套用連線狀態的變化，讓 CommandSystem 以相同的順序執行
+pub struct InputReplaySystem;
+
+impl<'a> System<'a> for InputReplaySystem {
+    type SystemData = (
+        WriteStorage<'a, Player>,
+        Write<'a, InputReplay>,
+        Write<'a, CommandQueue>,
+        Write<'a, InputLog>,
+        Read<'a, SimTime>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut players, mut replay, mut queue, mut input_log, sim_time) = data;
+        while let Some(record) = replay.records.get(replay.next).filter(|record| record.tick <= sim_time.tick).cloned() {
+            replay.next += 1;
+            match record.input {
+                PlayerInput::Command { command } => queue.push(record.player_id, command),
+                PlayerInput::Connected | PlayerInput::Disconnected => {
+                    if let Some(player) = (&mut players).join().find(|p| p.id == record.player_id) {
+                        player.connected = matches!(record.input, PlayerInput::Connected);
+                    }
+                    input_log.record(sim_time.tick, record.player_id, record.input);
+                }
+                PlayerInput::Abandoned => {
+                    if let Some(player) = (&mut players).join().find(|p| p.id == record.player_id) {
+                        player.connected = false;
+                        player.abandoned = true;
+                    }
+                    input_log.record(sim_time.tick, record.player_id, record.input);
+                }
+            }
+        }
+    }
+}
+
+// 執行所有玩家指令
+pub struct CommandSystem;
+
+impl<'a> System<'a> for CommandSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Player>,
+        ReadStorage<'a, Chess>,
+        ReadStorage<'a, Owner>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Bench>,
+        WriteStorage<'a, Equipment>,
+        ReadStorage<'a, TurnState>,
+        Read<'a, UnitDefinitions>,
+        Read<'a, ItemDefinitions>,
+        Write<'a, Carousel>,
+        ReadExpect<'a, ServerConfig>,
+        Write<'a, CommandQueue>,
+        Write<'a, InputLog>,
+        Read<'a, SimTime>,
+        Read<'a, ReplayRecorder>,
+        Read<'a, MatchHistory>,
+        Write<'a, ViewState>,
+        Read<'a, HashMap<String, WebsocketChannel>>,
+        Write<'a, SimRng>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut players, chess, owners, mut positions, mut benches, mut equipment, turn_states, unit_definitions, item_definitions, mut carousel, config, mut queue, mut input_log, sim_time, replays, history, mut view_state, websocket_channels, mut rng, lazy) = data;
+        let phase = (&turn_states).join().next().map(|turn_state| turn_state.current_phase);
+        // 本 tick 購買的棋子在 tick 結束後才生成，先保留備戰席位置
+        let mut reserved: Vec<(usize, usize)> = Vec::new();
+
+        for (player_id, command) in queue.pending.drain(..) {
+            let player = match (&mut players).join().find(|p| p.id == player_id) {
+                Some(player) => player,
+                None => continue,
+            };
+
+            // 找出該玩家擁有的棋子
+            let find_unit = |unit: Uuid| -> Option<Entity> {
+                (&entities, &chess, &owners)
+                    .join()
+                    .find(|(_, c, o)| c.id == unit && o.player_id == player_id)
+                    .map(|(e, _, _)| e)
+            };
+
+            // 重新同步、重播與對戰紀錄只影響畫面，不需要記錄
+            if !matches!(command, PlayerCommand::Resync | PlayerCommand::Replay { .. } | PlayerCommand::History) {
+                input_log.record(sim_time.tick, player_id, PlayerInput::Command { command: command.clone() });
+            }
+
+            let result = match command {
+                PlayerCommand::Resync => {
+                    view_state.request_snapshot(player_id);
+                    Ok(())
+                }
+                PlayerCommand::Equip { item, unit } => match (player.items.get(item).cloned(), find_unit(unit)) {
+                    (None, _) => Err(format!("no item in slot {}", item)),
+                    (_, None) => Err(format!("unit {} not found", unit)),
+                    // 戰鬥屬性在戰鬥開始時已計算，戰鬥中只能裝備到備戰席的棋子
+                    (Some(_), Some(entity)) if phase == Some(TurnPhase::Combat) && positions.contains(entity) => {
+                        Err("cannot equip units on the board during combat".to_string())
+                    }
+                    (Some(item_id), Some(entity)) => {
+                        let held = match equipment.get_mut(entity) {
+                            Some(held) => held,
+                            None => {
+                                let _ = equipment.insert(entity, Equipment::default());
+                                equipment.get_mut(entity).unwrap()
+                            }
+                        };
+                        held.equip(&item_id, &item_definitions).map(|_| {
+                            player.items.remove(item);
+                        })
+                    }
+                },
+                PlayerCommand::Replay { round } => match replays.find(player_id, round) {
+                    None => Err("no replay available".to_string()),
+                    Some(replay) => {
+                        let reply = serde_json::json!({ "type": "replay", "replay": replay });
+                        if let Some(channel) = websocket_channels.get(&player.name_id) {
+                            let _ = channel.tx_to_websocket.try_send(ChannelMessage::SpecsEvent(reply.to_string()));
+                        }
+                        Ok(())
+                    }
+                },
+                PlayerCommand::History => {
+                    let reply = serde_json::json!({ "type": "history", "rounds": history.for_player(player_id) });
+                    if let Some(channel) = websocket_channels.get(&player.name_id) {
+                        let _ = channel.tx_to_websocket.try_send(ChannelMessage::SpecsEvent(reply.to_string()));
+                    }
+                    Ok(())
+                }
+                PlayerCommand::Buy { slot } => {
+                    let definition = player.shop.get(slot).and_then(|unit_id| unit_definitions.get(unit_id));
+                    let free_slot = (0..BENCH_SIZE).find(|&slot| {
+                        !reserved.contains(&(player_id, slot))
+                            && !(&owners, &benches).join().any(|(o, b)| o.player_id == player_id && b.slot == slot)
+                    });
+                    match (definition, free_slot) {
+                        (None, _) => Err(format!("no unit in shop slot {}", slot)),
+                        (_, None) => Err("bench is full".to_string()),
+                        (Some(definition), _) if player.gold < definition.cost as i32 => Err("not enough gold".to_string()),
+                        (Some(definition), Some(bench_slot)) => {
+                            player.gold -= definition.cost as i32;
+                            player.shop.remove(slot);
+                            reserved.push((player_id, bench_slot));
+                            lazy.create_entity(&entities)
+                                .with(Chess {
+                                    id: rng.uuid(),
+                                    name: definition.name.clone(),
+                                    level: 1,
+                                    unit_id: definition.id.clone(),
+                                })
+                                .with(Owner { player_id })
+                                .with(Bench { slot: bench_slot })
+                                .with(definition.combat_stats(1))
+                                .with(StatusEffects { effects: Vec::new() })
+                                .with(Equipment::default())
+                                .build();
+                            Ok(())
+                        }
+                    }
+                }
+                PlayerCommand::Reroll => {
+                    if player.gold < config.reroll_cost {
+                        Err("not enough gold".to_string())
+                    } else {
+                        player.gold -= config.reroll_cost;
+                        player.shop = shop::roll_shop(player.level, &unit_definitions, &mut rng);
+                        Ok(())
+                    }
+                }
+                PlayerCommand::Level => {
+                    if shop::experience_to_next(player).is_none() {
+                        Err("already at max level".to_string())
+                    } else if player.gold < config.level_cost {
+                        Err("not enough gold".to_string())
+                    } else {
+                        player.gold -= config.level_cost;
+                        shop::add_experience(player, shop::EXPERIENCE_PER_PURCHASE);
+                        Ok(())
+                    }
+                }
+                // 戰鬥中場上的棋子不能移動
+                PlayerCommand::Move { .. } | PlayerCommand::AutoPlace if phase == Some(TurnPhase::Combat) => {
+                    Err("cannot move units during combat".to_string())
+                }
+                PlayerCommand::Move { unit, to } => match find_unit(unit) {
+                    None => Err(format!("unit {} not found", unit)),
+                    Some(entity) => move_unit(entity, to, player, &entities, &owners, &mut positions, &mut benches),
+                },
+                PlayerCommand::AutoPlace => {
+                    let units = formation::owned_units(player_id, &entities, &chess, &owners, &positions, &benches, &unit_definitions);
+                    let moves = formation::arrange(&units, player.level as usize);
+                    formation::apply(&moves, &entities, &chess, &mut positions, &mut benches);
+                    Ok(())
+                }
+                PlayerCommand::Pick { offer } => carousel.pick(player_id, offer, config.carousel_pick_time),
+                PlayerCommand::Sell { unit } => match find_unit(unit) {
+                    None => Err(format!("unit {} not found", unit)),
+                    // 戰鬥中場上的棋子正在對戰，只能賣出備戰席的棋子
+                    Some(entity) if phase == Some(TurnPhase::Combat) && positions.contains(entity) => {
+                        Err("cannot sell units on the board during combat".to_string())
+                    }
+                    Some(entity) => {
+                        let sold = chess.get(entity).unwrap();
+                        let cost = unit_definitions.get(&sold.unit_id).map_or(1, |d| d.cost) as i32;
+                        player.gold += cost * sold.level as i32;
+                        if let Some(held) = equipment.get_mut(entity) {
+                            player.items.append(&mut held.items);
+                        }
+                        entities.delete(entity).map_err(|e| e.to_string())
+                    }
+                },
+            };
+
+            // 拒絕的原因只回傳給送出指令的玩家，電腦玩家沒有連線也不輸出
+            if let Err(reason) = result {
+                if let Some(channel) = websocket_channels.get(&player.name_id) {
+                    let reply = serde_json::json!({ "type": "error", "reason": reason });
+                    let _ = channel.tx_to_websocket.try_send(ChannelMessage::SpecsEvent(reply.to_string()));
+                }
+            }
+        }
+    }
+}
+
+// 移動棋子；目標位置有自己的棋子時互換，從備戰席上場時場上棋子數不能超過玩家等級
+fn move_unit(
+    entity: Entity,
+    to: Placement,
+    player: &Player,
+    entities: &Entities,
+    owners: &ReadStorage<Owner>,
+    positions: &mut WriteStorage<Position>,
+    benches: &mut WriteStorage<Bench>,
+) -> Result<(), String> {
+    let placement_of = |entity: Entity, positions: &WriteStorage<Position>, benches: &WriteStorage<Bench>| {
+        match (positions.get(entity), benches.get(entity)) {
+            (Some(position), _) => Some(Placement::Board { x: position.x, y: position.y }),
+            (_, Some(bench)) => Some(Placement::Bench { slot: bench.slot }),
+            _ => None,
+        }
+    };
+    match to {
+        Placement::Board { x, y } if !(0..BOARD_WIDTH).contains(&x) || !(0..BOARD_HEIGHT).contains(&y) => {
+            return Err(format!("position ({}, {}) is off the board", x, y));
+        }
+        Placement::Bench { slot } if slot >= BENCH_SIZE => return Err(format!("no bench slot {}", slot)),
+        _ => {}
+    }
+    let from = placement_of(entity, positions, benches).ok_or_else(|| "unit cannot be moved".to_string())?;
+    if from == to {
+        return Ok(());
+    }
+
+    let occupant = (entities, owners)
+        .join()
+        .filter(|(other, owner)| *other != entity && owner.player_id == player.id)
+        .map(|(other, _)| other)
+        .find(|&other| placement_of(other, positions, benches) == Some(to));
+    if occupant.is_none() && matches!(from, Placement::Bench { .. }) && matches!(to, Placement::Board { .. }) {
+        let on_board = (owners, &*positions).join().filter(|(owner, _)| owner.player_id == player.id).count();
+        if on_board >= player.level as usize {
+            return Err(format!("board is full ({} units at level {})", on_board, player.level));
+        }
+    }
+
+    place(entity, to, positions, benches);
+    if let Some(occupant) = occupant {
+        place(occupant, from, positions, benches);
+    }
+    Ok(())
+}
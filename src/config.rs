@@ -0,0 +1,364 @@
+use serde::{Serialize, Deserialize};
+use std::net::SocketAddr;
+use std::time::Duration;
+use crate::bots::BotProfile;
+
+// 伺服器設定：先讀預設值，再依序套用設定檔、環境變數、命令列參數
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    pub tick_rate: u32,               // 每秒模擬幾個固定 tick，每個 tick 推進 1 / tick_rate 秒的模擬時間
+    pub num_players: usize,
+    pub channel_capacity: usize,      // 每位玩家 WebSocket 與模擬端之間的通道容量
+    pub selection_time: f32,          // 開局選擇階段時間（秒）
+    pub preparation_time: f32,        // 準備階段時間（秒）
+    pub combat_time: f32,             // 戰鬥階段時間（秒）
+    pub resolution_time: f32,         // 結算階段時間（秒）
+    pub disconnect_grace_secs: u64,   // 斷線後保留會話的時間
+    pub spectator_delay_secs: u64,    // 觀戰延遲
+    pub heartbeat_interval_secs: u64, // 每隔多久送出一次 ping
+    pub idle_timeout_secs: u64,       // 超過這段時間沒收到任何訊框就視為斷線
+    pub data_dir: String,             // 棋子等遊戲資料所在目錄
+    pub reload_interval_secs: u64,    // 每隔多久檢查資料檔是否變動，0 表示只在收到 SIGHUP 時重新載入
+    pub item_drop_chance: f32,        // 每次結算時每位玩家獲得一個基礎道具的機率
+    pub pve_rounds: Vec<u32>,         // 固定為 PvE（對戰野怪）的回合
+    pub pve_round_interval: u32,      // 每隔幾回合一次 PvE，0 表示只用 pve_rounds
+    pub pvp_base_damage: i32,         // PvP 落敗時的基礎扣血，另加對手存活棋子的星級總和
+    pub carousel_rounds: Vec<u32>,    // 準備階段前先進行共享輪抽的回合
+    pub carousel_round_interval: u32, // 每隔幾回合一次輪抽，0 表示只用 carousel_rounds
+    pub carousel_pick_time: f32,      // 輪抽時每位玩家的選擇時間（秒）
+    pub mana_per_attack: i32,         // 普通攻擊命中時攻擊者獲得的魔力
+    pub mana_per_damage_taken: f32,   // 受到傷害時每點減免前傷害獲得的魔力
+    pub max_mana_per_hit: i32,        // 單次受擊最多獲得的魔力
+    pub move_time: f32,               // 戰鬥中棋子每移動一格所需的時間（秒）
+    pub round_income: i32,            // 每回合準備階段的基本收入
+    pub max_interest: i32,            // 每 10 金幣 1 金幣利息的上限
+    pub reroll_cost: i32,             // 刷新商店的費用
+    pub level_cost: i32,              // 購買一次經驗的費用
+    pub bots: Vec<String>,            // 由電腦控制的玩家難度，依序分配給編號最大的玩家
+    pub replay_dir: Option<String>,   // 每場戰鬥的重播檔輸出目錄，未設定時只保留在記憶體中
+    pub seed: Option<u64>,            // 模擬亂數種子，未設定時隨機產生；相同種子與相同指令會重現相同的對局
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_address: "127.0.0.1:8080".to_string(),
+            tick_rate: 30,
+            num_players: 4,
+            channel_capacity: 32,
+            selection_time: 10.0,
+            preparation_time: 30.0,
+            combat_time: 60.0,
+            resolution_time: 5.0,
+            disconnect_grace_secs: 60,
+            spectator_delay_secs: 0,
+            heartbeat_interval_secs: 15,
+            idle_timeout_secs: 45,
+            data_dir: "data".to_string(),
+            reload_interval_secs: 5,
+            item_drop_chance: 0.3,
+            pve_rounds: vec![1, 2, 3],
+            pve_round_interval: 10,
+            pvp_base_damage: 2,
+            carousel_rounds: Vec::new(),
+            carousel_round_interval: 6,
+            carousel_pick_time: 5.0,
+            mana_per_attack: 5,
+            mana_per_damage_taken: 0.1,
+            max_mana_per_hit: 10,
+            move_time: 0.5,
+            round_income: 5,
+            max_interest: 5,
+            reroll_cost: 2,
+            level_cost: 4,
+            bots: Vec::new(),
+            replay_dir: None,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: String, error: std::io::Error },
+    Parse { path: String, error: serde_json::Error },
+    MissingValue(String),
+    UnknownArgument(String),
+    InvalidValue { key: String, value: String },
+    Invalid { field: &'static str, reason: String },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::Io { path, error } => write!(f, "cannot read config file {}: {}", path, error),
+            ConfigError::Parse { path, error } => write!(f, "invalid config file {}: {}", path, error),
+            ConfigError::MissingValue(arg) => write!(f, "missing value for {}", arg),
+            ConfigError::UnknownArgument(arg) => write!(f, "unknown argument {}", arg),
+            ConfigError::InvalidValue { key, value } => write!(f, "invalid value for {}: {:?}", key, value),
+            ConfigError::Invalid { field, reason } => write!(f, "invalid {}: {}", field, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// 命令列參數與環境變數對應的設定欄位
+const OVERRIDES: &[(&str, &str)] = &[
+    ("--bind", "AC_BIND_ADDRESS"),
+    ("--tick-rate", "AC_TICK_RATE"),
+    ("--players", "AC_NUM_PLAYERS"),
+    ("--channel-capacity", "AC_CHANNEL_CAPACITY"),
+    ("--selection-time", "AC_SELECTION_TIME"),
+    ("--preparation-time", "AC_PREPARATION_TIME"),
+    ("--combat-time", "AC_COMBAT_TIME"),
+    ("--resolution-time", "AC_RESOLUTION_TIME"),
+    ("--disconnect-grace", "AC_DISCONNECT_GRACE_SECS"),
+    ("--spectator-delay", "AC_SPECTATOR_DELAY_SECS"),
+    ("--heartbeat-interval", "AC_HEARTBEAT_INTERVAL_SECS"),
+    ("--idle-timeout", "AC_IDLE_TIMEOUT_SECS"),
+    ("--data-dir", "AC_DATA_DIR"),
+    ("--reload-interval", "AC_RELOAD_INTERVAL_SECS"),
+    ("--item-drop-chance", "AC_ITEM_DROP_CHANCE"),
+    ("--pve-rounds", "AC_PVE_ROUNDS"),
+    ("--pve-round-interval", "AC_PVE_ROUND_INTERVAL"),
+    ("--pvp-base-damage", "AC_PVP_BASE_DAMAGE"),
+    ("--carousel-rounds", "AC_CAROUSEL_ROUNDS"),
+    ("--carousel-round-interval", "AC_CAROUSEL_ROUND_INTERVAL"),
+    ("--carousel-pick-time", "AC_CAROUSEL_PICK_TIME"),
+    ("--mana-per-attack", "AC_MANA_PER_ATTACK"),
+    ("--mana-per-damage-taken", "AC_MANA_PER_DAMAGE_TAKEN"),
+    ("--max-mana-per-hit", "AC_MAX_MANA_PER_HIT"),
+    ("--move-time", "AC_MOVE_TIME"),
+    ("--round-income", "AC_ROUND_INCOME"),
+    ("--max-interest", "AC_MAX_INTEREST"),
+    ("--reroll-cost", "AC_REROLL_COST"),
+    ("--level-cost", "AC_LEVEL_COST"),
+    ("--bots", "AC_BOTS"),
+    ("--replay-dir", "AC_REPLAY_DIR"),
+    ("--seed", "AC_SEED"),
+];
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+    })
+}
+
+// 逗號分隔的清單，例如 "1,2,3"，空字串表示空清單
+fn parse_list<T: std::str::FromStr>(key: &str, value: &str) -> Result<Vec<T>, ConfigError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| parse(key, part))
+        .collect()
+}
+
+impl ServerConfig {
+    // 從程式的命令列參數與環境變數載入設定
+    pub fn load() -> Result<Self, ConfigError> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let env: Vec<(String, String)> = std::env::vars().collect();
+        Self::load_from(&args, &env)
+    }
+
+    pub fn load_from(args: &[String], env: &[(String, String)]) -> Result<Self, ConfigError> {
+        let env_value = |name: &str| env.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+
+        // 先把命令列參數整理成 (旗標, 值)
+        let mut cli: Vec<(String, String)> = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), value.to_string()),
+                None => {
+                    let value = iter.next().ok_or_else(|| ConfigError::MissingValue(arg.clone()))?;
+                    (arg.clone(), value.clone())
+                }
+            };
+            if flag != "--config" && !OVERRIDES.iter().any(|(f, _)| *f == flag) {
+                return Err(ConfigError::UnknownArgument(flag));
+            }
+            cli.push((flag, value));
+        }
+        let cli_value = |flag: &str| cli.iter().rev().find(|(f, _)| f == flag).map(|(_, v)| v.clone());
+
+        let mut config = match cli_value("--config").or_else(|| env_value("AC_CONFIG")) {
+            Some(path) => Self::from_file(&path)?,
+            None => ServerConfig::default(),
+        };
+
+        for (flag, env_name) in OVERRIDES {
+            if let Some(value) = env_value(env_name) {
+                config.set(env_name, &value)?;
+            }
+            if let Some(value) = cli_value(flag) {
+                config.set(flag, &value)?;
+            }
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|error| ConfigError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        serde_json::from_str(&text).map_err(|error| ConfigError::Parse {
+            path: path.to_string(),
+            error,
+        })
+    }
+
+    // key 為命令列旗標或環境變數名稱
+    fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let flag = OVERRIDES
+            .iter()
+            .find(|(flag, env_name)| *flag == key || *env_name == key)
+            .map(|(flag, _)| *flag)
+            .unwrap_or(key);
+
+        match flag {
+            "--bind" => self.bind_address = value.to_string(),
+            "--tick-rate" => self.tick_rate = parse(key, value)?,
+            "--players" => self.num_players = parse(key, value)?,
+            "--channel-capacity" => self.channel_capacity = parse(key, value)?,
+            "--selection-time" => self.selection_time = parse(key, value)?,
+            "--preparation-time" => self.preparation_time = parse(key, value)?,
+            "--combat-time" => self.combat_time = parse(key, value)?,
+            "--resolution-time" => self.resolution_time = parse(key, value)?,
+            "--disconnect-grace" => self.disconnect_grace_secs = parse(key, value)?,
+            "--spectator-delay" => self.spectator_delay_secs = parse(key, value)?,
+            "--heartbeat-interval" => self.heartbeat_interval_secs = parse(key, value)?,
+            "--idle-timeout" => self.idle_timeout_secs = parse(key, value)?,
+            "--data-dir" => self.data_dir = value.to_string(),
+            "--reload-interval" => self.reload_interval_secs = parse(key, value)?,
+            "--item-drop-chance" => self.item_drop_chance = parse(key, value)?,
+            "--pve-rounds" => self.pve_rounds = parse_list(key, value)?,
+            "--pve-round-interval" => self.pve_round_interval = parse(key, value)?,
+            "--pvp-base-damage" => self.pvp_base_damage = parse(key, value)?,
+            "--carousel-rounds" => self.carousel_rounds = parse_list(key, value)?,
+            "--carousel-round-interval" => self.carousel_round_interval = parse(key, value)?,
+            "--carousel-pick-time" => self.carousel_pick_time = parse(key, value)?,
+            "--mana-per-attack" => self.mana_per_attack = parse(key, value)?,
+            "--mana-per-damage-taken" => self.mana_per_damage_taken = parse(key, value)?,
+            "--max-mana-per-hit" => self.max_mana_per_hit = parse(key, value)?,
+            "--move-time" => self.move_time = parse(key, value)?,
+            "--round-income" => self.round_income = parse(key, value)?,
+            "--max-interest" => self.max_interest = parse(key, value)?,
+            "--reroll-cost" => self.reroll_cost = parse(key, value)?,
+            "--level-cost" => self.level_cost = parse(key, value)?,
+            "--bots" => self.bots = parse_list(key, value)?,
+            "--replay-dir" => self.replay_dir = Some(value.to_string()),
+            "--seed" => self.seed = Some(parse(key, value)?),
+            _ => return Err(ConfigError::UnknownArgument(key.to_string())),
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |field: &'static str, reason: &str| {
+            Err(ConfigError::Invalid { field, reason: reason.to_string() })
+        };
+
+        if self.bind_address.parse::<SocketAddr>().is_err() {
+            return invalid("bind_address", "expected host:port, e.g. 127.0.0.1:8080");
+        }
+        if self.tick_rate == 0 || self.tick_rate > 1000 {
+            return invalid("tick_rate", "must be between 1 and 1000");
+        }
+        if self.num_players == 0 {
+            return invalid("num_players", "must be at least 1");
+        }
+        if self.channel_capacity == 0 {
+            return invalid("channel_capacity", "must be at least 1");
+        }
+        for (field, value) in [
+            ("selection_time", self.selection_time),
+            ("preparation_time", self.preparation_time),
+            ("combat_time", self.combat_time),
+            ("resolution_time", self.resolution_time),
+            ("carousel_pick_time", self.carousel_pick_time),
+            ("move_time", self.move_time),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                return invalid(field, "must be a positive number of seconds");
+            }
+        }
+        if !(0.0..=1.0).contains(&self.item_drop_chance) {
+            return invalid("item_drop_chance", "must be between 0 and 1");
+        }
+        if self.pvp_base_damage < 0 {
+            return invalid("pvp_base_damage", "must not be negative");
+        }
+        if self.mana_per_attack < 0 {
+            return invalid("mana_per_attack", "must not be negative");
+        }
+        if !(self.mana_per_damage_taken.is_finite() && self.mana_per_damage_taken >= 0.0) {
+            return invalid("mana_per_damage_taken", "must not be negative");
+        }
+        if self.max_mana_per_hit < 0 {
+            return invalid("max_mana_per_hit", "must not be negative");
+        }
+        for (field, value) in [
+            ("round_income", self.round_income),
+            ("max_interest", self.max_interest),
+            ("reroll_cost", self.reroll_cost),
+            ("level_cost", self.level_cost),
+        ] {
+            if value < 0 {
+                return invalid(field, "must not be negative");
+            }
+        }
+        if self.bots.len() > self.num_players {
+            return invalid("bots", "cannot have more bots than players");
+        }
+        if let Some(name) = self.bots.iter().find(|name| BotProfile::named(name).is_none()) {
+            return invalid("bots", &format!("unknown bot profile {:?}, expected one of {}", name, BotProfile::NAMES.join(", ")));
+        }
+        if self.heartbeat_interval_secs == 0 {
+            return invalid("heartbeat_interval_secs", "must be at least 1");
+        }
+        if self.idle_timeout_secs <= self.heartbeat_interval_secs {
+            return invalid("idle_timeout_secs", "must be longer than heartbeat_interval_secs");
+        }
+        Ok(())
+    }
+
+    // 某回合是否為 PvE 回合
+    pub fn is_pve_round(&self, round: u32) -> bool {
+        self.pve_rounds.contains(&round)
+            || (self.pve_round_interval > 0 && round % self.pve_round_interval == 0)
+    }
+
+    // 某回合開始前是否進行共享輪抽
+    pub fn is_carousel_round(&self, round: u32) -> bool {
+        self.carousel_rounds.contains(&round)
+            || (self.carousel_round_interval > 0 && round % self.carousel_round_interval == 0)
+    }
+
+    // 每個 tick 推進的模擬時間（秒）
+    pub fn tick_seconds(&self) -> f32 {
+        1.0 / self.tick_rate as f32
+    }
+
+    // 電腦玩家的編號與難度，分配給編號最大的玩家
+    pub fn bot_players(&self) -> Vec<(usize, BotProfile)> {
+        let first = self.num_players - self.bots.len().min(self.num_players);
+        self.bots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| BotProfile::named(name).map(|profile| (first + i, profile)))
+            .collect()
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_secs_f32(self.tick_seconds())
+    }
+}
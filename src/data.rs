@@ -0,0 +1,211 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use specs::{World, WorldExt};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::units::UnitDefinitions;
+use crate::synergy::TraitDefinitions;
+use crate::items::ItemDefinitions;
+use crate::rounds::CreepBoards;
+use crate::skills::SkillRegistry;
+
+// 讀取遊戲資料檔時的錯誤，訊息中包含檔案路徑方便企劃定位
+#[derive(Debug)]
+pub enum DataError {
+    Io { path: PathBuf, error: std::io::Error },
+    Parse { path: PathBuf, error: serde_json::Error },
+    Duplicate { id: String, first: PathBuf, second: PathBuf },
+    Invalid { path: PathBuf, id: String, reason: String },
+}
+
+impl std::fmt::Display for DataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DataError::Io { path, error } => write!(f, "cannot read {}: {}", path.display(), error),
+            DataError::Parse { path, error } => write!(f, "invalid JSON in {}: {}", path.display(), error),
+            DataError::Duplicate { id, first, second } => write!(
+                f,
+                "duplicate id {:?} in {} (first defined in {})",
+                id,
+                second.display(),
+                first.display()
+            ),
+            DataError::Invalid { path, id, reason } => write!(f, "{:?} in {}: {}", id, path.display(), reason),
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+// 讀取目錄下所有 .json 檔，每個檔案是一個定義陣列；依檔名排序確保載入順序固定
+pub fn load_json_dir<T: DeserializeOwned>(dir: &Path) -> Result<Vec<(PathBuf, T)>, DataError> {
+    let entries = std::fs::read_dir(dir).map_err(|error| DataError::Io {
+        path: dir.to_path_buf(),
+        error,
+    })?;
+
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|error| DataError::Io {
+            path: dir.to_path_buf(),
+            error,
+        })?;
+        let path = entry.path();
+        if path.extension().map_or(false, |ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut definitions = Vec::new();
+    for path in paths {
+        let text = std::fs::read_to_string(&path).map_err(|error| DataError::Io {
+            path: path.clone(),
+            error,
+        })?;
+        let items: Vec<T> = serde_json::from_str(&text).map_err(|error| DataError::Parse {
+            path: path.clone(),
+            error,
+        })?;
+        definitions.extend(items.into_iter().map(|item| (path.clone(), item)));
+    }
+    Ok(definitions)
+}
+
+// 目前使用中的遊戲資料指紋，寫入輸入紀錄與戰鬥重播
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DataFingerprint(pub String);
+
+// 資料目錄下的所有遊戲資料：units/、traits/、items/ 與 creeps/
+#[derive(Debug, Clone)]
+pub struct GameData {
+    pub units: UnitDefinitions,
+    pub traits: TraitDefinitions,
+    pub items: ItemDefinitions,
+    pub creeps: CreepBoards,
+}
+
+impl GameData {
+    // 載入所有資料並檢查彼此之間的引用，以及棋子的技能是否已註冊
+    pub fn load(dir: &Path, skills: &SkillRegistry) -> Result<Self, DataError> {
+        let units_dir = dir.join("units");
+        let units = UnitDefinitions::load_dir(&units_dir)?;
+        let traits = TraitDefinitions::load_dir(&dir.join("traits"))?;
+        let items = ItemDefinitions::load_dir(&dir.join("items"))?;
+        let creeps_dir = dir.join("creeps");
+        let creeps = CreepBoards::load_dir(&creeps_dir)?;
+
+        for unit in units.iter() {
+            if !skills.contains(&unit.skill.id) {
+                return Err(DataError::Invalid {
+                    path: units_dir,
+                    id: unit.id.clone(),
+                    reason: format!("unknown skill {:?} (registered: {})", unit.skill.id, skills.ids().join(", ")),
+                });
+            }
+            if let Some(unknown) = unit.traits.iter().find(|t| !traits.contains(t)) {
+                return Err(DataError::Invalid {
+                    path: units_dir,
+                    id: unit.id.clone(),
+                    reason: format!("unknown trait {:?}", unknown),
+                });
+            }
+        }
+
+        for board in creeps.iter() {
+            let unknown_unit = board.units.iter().map(|u| &u.unit_id).find(|id| units.get(id).is_none());
+            let unknown_item = board.reward.items.iter().find(|id| items.get(id).is_none());
+            let reason = match (unknown_unit, unknown_item) {
+                (Some(unit), _) => format!("unknown unit {:?}", unit),
+                (_, Some(item)) => format!("unknown reward item {:?}", item),
+                (None, None) => continue,
+            };
+            return Err(DataError::Invalid {
+                path: creeps_dir,
+                id: board.id.clone(),
+                reason,
+            });
+        }
+
+        Ok(GameData { units, traits, items, creeps })
+    }
+
+    // 資料內容的指紋（十六進位的 FNV-1a 雜湊）。定義以 id 排序後序列化，
+    // 內容相同的資料在任何機器上都得到相同的指紋，用來確認重現對局時使用的是同一份資料
+    pub fn fingerprint(&self) -> String {
+        let content = serde_json::json!({
+            "units": self.units.definitions(),
+            "traits": self.traits.definitions(),
+            "items": self.items.definitions(),
+            "creeps": self.creeps.definitions(),
+        });
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in content.to_string().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        format!("{:016x}", hash)
+    }
+
+    // 將各類定義放入 world 作為資源，並記下資料的指紋
+    pub fn insert_into(self, world: &mut World) {
+        world.insert(DataFingerprint(self.fingerprint()));
+        world.insert(self.units);
+        world.insert(self.traits);
+        world.insert(self.items);
+        world.insert(self.creeps);
+    }
+
+    // 列出與新資料之間的差異，例如 "units.mage.stats.hp: 70 -> 75"
+    pub fn diff(&self, new: &GameData) -> Vec<String> {
+        let mut changes = diff_definitions("units", self.units.definitions(), new.units.definitions());
+        changes.extend(diff_definitions("traits", self.traits.definitions(), new.traits.definitions()));
+        changes.extend(diff_definitions("items", self.items.definitions(), new.items.definitions()));
+        changes.extend(diff_definitions("creeps", self.creeps.definitions(), new.creeps.definitions()));
+        changes
+    }
+}
+
+pub fn diff_definitions<T: Serialize>(
+    prefix: &str,
+    old: &BTreeMap<String, T>,
+    new: &BTreeMap<String, T>,
+) -> Vec<String> {
+    let mut changes = Vec::new();
+    for (id, old_definition) in old {
+        let path = format!("{}.{}", prefix, id);
+        match new.get(id) {
+            Some(new_definition) => {
+                let old_value = serde_json::to_value(old_definition).unwrap_or_default();
+                let new_value = serde_json::to_value(new_definition).unwrap_or_default();
+                diff_values(&path, &old_value, &new_value, &mut changes);
+            }
+            None => changes.push(format!("{}: removed", path)),
+        }
+    }
+    for id in new.keys() {
+        if !old.contains_key(id) {
+            changes.push(format!("{}.{}: added", prefix, id));
+        }
+    }
+    changes
+}
+
+fn diff_values(path: &str, old: &serde_json::Value, new: &serde_json::Value, changes: &mut Vec<String>) {
+    use serde_json::Value;
+    match (old, new) {
+        (Value::Object(old_fields), Value::Object(new_fields)) => {
+            for (key, old_field) in old_fields {
+                let new_field = new_fields.get(key).unwrap_or(&Value::Null);
+                diff_values(&format!("{}.{}", path, key), old_field, new_field, changes);
+            }
+            for (key, new_field) in new_fields {
+                if !old_fields.contains_key(key) {
+                    diff_values(&format!("{}.{}", path, key), &Value::Null, new_field, changes);
+                }
+            }
+        }
+        _ if old != new => changes.push(format!("{}: {} -> {}", path, old, new)),
+        _ => {}
+    }
+}
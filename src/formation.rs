@@ -0,0 +1,215 @@
+use specs::{Entities, Entity, Join, ReadStorage, WriteStorage};
+use specs::storage::GenericReadStorage;
+use uuid::Uuid;
+use crate::{Chess, Owner, Position, Bench};
+use crate::commands::Placement;
+use crate::game_state::{BOARD_WIDTH, BOARD_HEIGHT};
+use crate::units::{UnitDefinition, UnitDefinitions};
+
+// 棋子在陣型中的位置：坦克站最前排，遠程站最後排
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Tank,
+    Melee,
+    Ranged,
+}
+
+impl Role {
+    // 攻擊距離 2 以上為遠程；近戰中防禦高於攻擊的為坦克
+    pub fn of(definition: &UnitDefinition) -> Role {
+        if definition.stats.attack_range >= 2.0 {
+            Role::Ranged
+        } else if definition.stats.defense > definition.stats.attack {
+            Role::Tank
+        } else {
+            Role::Melee
+        }
+    }
+
+    // 依偏好順序排列的列。y = 0 為最前排，戰鬥時雙方的最前排面對面（見 game_state::arena_position）
+    fn rows(self) -> Vec<i32> {
+        match self {
+            Role::Tank | Role::Melee => (0..BOARD_HEIGHT).collect(),
+            Role::Ranged => (0..BOARD_HEIGHT).rev().collect(),
+        }
+    }
+}
+
+// 棋子的價值：費用乘上 3 的（星級 - 1）次方
+pub fn unit_value(cost: u32, level: u32) -> u32 {
+    cost * 3u32.pow(level.saturating_sub(1))
+}
+
+// 排陣型時需要的棋子資訊
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormationUnit {
+    pub id: Uuid,
+    pub role: Role,
+    pub value: u32,
+    pub placement: Placement,
+}
+
+impl FormationUnit {
+    // 找不到棋子定義時視為 1 費近戰
+    pub fn new(chess: &Chess, placement: Placement, unit_definitions: &UnitDefinitions) -> Self {
+        let definition = unit_definitions.get(&chess.unit_id);
+        FormationUnit {
+            id: chess.id,
+            role: definition.map_or(Role::Melee, Role::of),
+            value: unit_value(definition.map_or(1, |d| d.cost), chess.level),
+            placement,
+        }
+    }
+
+    pub fn on_board(&self) -> bool {
+        matches!(self.placement, Placement::Board { .. })
+    }
+}
+
+// 玩家在棋盤與備戰席上的所有棋子
+pub fn owned_units<P, B>(
+    player_id: usize,
+    entities: &Entities,
+    chess: &ReadStorage<Chess>,
+    owners: &ReadStorage<Owner>,
+    positions: &P,
+    benches: &B,
+    unit_definitions: &UnitDefinitions,
+) -> Vec<FormationUnit>
+where
+    P: GenericReadStorage<Component = Position>,
+    B: GenericReadStorage<Component = Bench>,
+{
+    (entities, chess, owners)
+        .join()
+        .filter(|(_, _, owner)| owner.player_id == player_id)
+        .filter_map(|(entity, chess, _)| {
+            let placement = match (positions.get(entity), benches.get(entity)) {
+                (Some(position), _) => Placement::Board { x: position.x, y: position.y },
+                (_, Some(bench)) => Placement::Bench { slot: bench.slot },
+                _ => return None,
+            };
+            Some(FormationUnit::new(chess, placement, unit_definitions))
+        })
+        .collect()
+}
+
+// 角色偏好的第一個空格。先在棋盤格狀交錯的格子中找，讓棋子彼此不相鄰以減少範圍傷害，
+// 都滿了才使用其餘的格子；同一列中越靠近中央越優先
+pub fn free_cell(role: Role, occupied: &[(i32, i32)]) -> Option<(i32, i32)> {
+    let mut columns: Vec<i32> = (0..BOARD_WIDTH).collect();
+    columns.sort_by_key(|&x| ((2 * x - (BOARD_WIDTH - 1)).abs(), x));
+    let rows = role.rows();
+    [true, false]
+        .iter()
+        .flat_map(|&spread| {
+            let columns = &columns;
+            rows.iter().flat_map(move |&y| {
+                columns.iter().filter(move |&&x| (x + y) % 2 == 0 || !spread).map(move |&x| (x, y))
+            })
+        })
+        .find(|cell| !occupied.contains(cell))
+}
+
+// 依角色與價值決定上場順序：坦克、近戰、遠程，同角色價值高的先選格子
+fn placement_order(units: &mut Vec<&FormationUnit>) {
+    units.sort_by_key(|u| (u.role, std::cmp::Reverse(u.value), u.id));
+}
+
+// 完整陣型：價值最高的 limit 個棋子上場（價值相同時已在場上的優先）並依角色重新排列，
+// 其餘回到備戰席，使用上場棋子空出的位置。回傳需要變動的棋子與新位置，已排好時為空
+pub fn arrange(units: &[FormationUnit], limit: usize) -> Vec<(Uuid, Placement)> {
+    let mut ranked: Vec<&FormationUnit> = units.iter().collect();
+    ranked.sort_by_key(|u| (std::cmp::Reverse(u.value), !u.on_board(), u.id));
+    let (selected, rest) = ranked.split_at(limit.min(ranked.len()));
+
+    let mut selected = selected.to_vec();
+    placement_order(&mut selected);
+    let mut occupied: Vec<(i32, i32)> = Vec::new();
+    let mut targets: Vec<(Uuid, Placement)> = Vec::new();
+    for unit in selected {
+        if let Some((x, y)) = free_cell(unit.role, &occupied) {
+            occupied.push((x, y));
+            targets.push((unit.id, Placement::Board { x, y }));
+        }
+    }
+
+    // 上場的棋子空出的備戰席位置，依序分給下場的棋子
+    let mut vacated: Vec<usize> = units
+        .iter()
+        .filter_map(|u| match u.placement {
+            Placement::Bench { slot } if targets.iter().any(|(id, _)| *id == u.id) => Some(slot),
+            _ => None,
+        })
+        .collect();
+    vacated.sort_unstable();
+    let mut vacated = vacated.into_iter();
+    for unit in rest {
+        match unit.placement {
+            Placement::Bench { .. } => targets.push((unit.id, unit.placement)),
+            Placement::Board { .. } => {
+                if let Some(slot) = vacated.next() {
+                    targets.push((unit.id, Placement::Bench { slot }));
+                }
+            }
+        }
+    }
+
+    targets
+        .into_iter()
+        .filter(|(id, placement)| units.iter().any(|u| u.id == *id && u.placement != *placement))
+        .collect()
+}
+
+// 補位：保留玩家已擺好的棋子，場上未滿 limit 時依價值讓備戰席的棋子補上角色偏好的空格
+pub fn fill(units: &[FormationUnit], limit: usize) -> Vec<(Uuid, Placement)> {
+    let mut occupied: Vec<(i32, i32)> = units
+        .iter()
+        .filter_map(|u| match u.placement {
+            Placement::Board { x, y } => Some((x, y)),
+            Placement::Bench { .. } => None,
+        })
+        .collect();
+    let mut bench: Vec<&FormationUnit> = units.iter().filter(|u| !u.on_board()).collect();
+    bench.sort_by_key(|u| (std::cmp::Reverse(u.value), u.id));
+    bench.truncate(limit.saturating_sub(occupied.len()));
+    placement_order(&mut bench);
+
+    let mut moves = Vec::new();
+    for unit in bench {
+        if let Some((x, y)) = free_cell(unit.role, &occupied) {
+            occupied.push((x, y));
+            moves.push((unit.id, Placement::Board { x, y }));
+        }
+    }
+    moves
+}
+
+// 套用 arrange 或 fill 的結果
+pub fn apply(
+    moves: &[(Uuid, Placement)],
+    entities: &Entities,
+    chess: &ReadStorage<Chess>,
+    positions: &mut WriteStorage<Position>,
+    benches: &mut WriteStorage<Bench>,
+) {
+    for (entity, chess) in (entities, chess).join() {
+        if let Some((_, placement)) = moves.iter().find(|(id, _)| *id == chess.id) {
+            place(entity, *placement, positions, benches);
+        }
+    }
+}
+
+// 把棋子放到棋盤或備戰席的位置，不檢查是否已有其他棋子
+pub fn place(entity: Entity, placement: Placement, positions: &mut WriteStorage<Position>, benches: &mut WriteStorage<Bench>) {
+    match placement {
+        Placement::Board { x, y } => {
+            benches.remove(entity);
+            let _ = positions.insert(entity, Position { x, y });
+        }
+        Placement::Bench { slot } => {
+            positions.remove(entity);
+            let _ = benches.insert(entity, Bench { slot });
+        }
+    }
+}
@@ -1,15 +1,80 @@
 use specs::{World, WorldExt, Builder, Join};
 use uuid::Uuid;
 use std::collections::HashMap; // Import HashMap
-use crate::{Position, Chess, ChessType, CombatStats, StatusEffects, Skill, SkillType};
+use crate::{Position, Chess, CombatStats, StatusEffects, Owner, Bench};
+use crate::units::UnitDefinitions;
+use crate::data::{DataFingerprint, GameData};
+use crate::stats;
+use crate::shop;
+use crate::bots::Bot;
+use crate::formation::{self, Role};
+use crate::synergy::{self, Synergies, TraitDefinitions};
+use crate::items::{Equipment, ItemDefinitions};
+use crate::commands::{CommandQueue, InputLog};
+use crate::carousel::{Carousel, CarouselOffer};
+use crate::rng::SimRng;
+use crate::combat::Casting;
+use crate::targeting::CurrentTarget;
+use crate::combat_log::CombatLog;
+use crate::replay::{self, CombatReplay, ReplayRecorder, ReplaySide, ReplayUnit, REPLAY_VERSION};
+use crate::battle_stats::BattleStats;
+use crate::rounds::{Team, Creep, Summoned, Matchup, MatchupResult, Matchups, MatchHistory, Opponent, CreepBoards, Standing, Standings};
+use crate::view::{PhaseTimer, ViewState};
 use crate::turn::{TurnState, TurnPhase, Player, TurnManager};
 use crate::{ChannelMessage, WebsocketChannel}; // Import the ChannelMessage enum and SpecsChannel
+use crate::config::ServerConfig;
+
+// 模擬時間：目前的 tick 與每個 tick 推進的固定秒數，由 GameState::update 寫入
+#[derive(Debug, Default)]
+pub struct SimTime {
+    pub tick: u64,
+    pub delta: f32,
+}
+
+// 每位玩家備戰席的格數
+pub const BENCH_SIZE: usize = 9;
+
+// 每位玩家棋盤的大小，y = 0 為最前排
+pub const BOARD_WIDTH: i32 = 8;
+pub const BOARD_HEIGHT: i32 = 4;
+
+// 棋盤座標轉為戰鬥場地座標。雙方的最前排面對面：主場（side 0）翻轉到 y = 0..BOARD_HEIGHT，
+// 最前排在 y = BOARD_HEIGHT - 1；客場接在後面，最前排在 y = BOARD_HEIGHT。伺服器與平衡測試共用
+pub fn arena_position(side: usize, (x, y): (i32, i32)) -> (i32, i32) {
+    match side {
+        0 => (x, BOARD_HEIGHT - 1 - y),
+        _ => (x, BOARD_HEIGHT + y),
+    }
+}
+
+// arena_position 的反向轉換，戰鬥結束後把棋子放回自己的棋盤
+pub fn board_position(side: usize, (x, y): (i32, i32)) -> (i32, i32) {
+    match side {
+        0 => (x, BOARD_HEIGHT - 1 - y),
+        _ => (x, y - BOARD_HEIGHT),
+    }
+}
+
+// 遊戲過程的訊息；quiet 時不輸出，供無網路的大量對局使用
+macro_rules! game_log {
+    ($state:expr, $($arg:tt)*) => {
+        if !$state.quiet {
+            println!($($arg)*);
+        }
+    };
+}
 
 pub struct GameState {
     pub world: World,
     pub turn_manager: TurnManager,
     pub mode: Mode,
     pub mode_timer: f32,
+    data_reloads: Option<tokio::sync::mpsc::Receiver<GameData>>, // 熱更新送來的遊戲資料
+    pending_data: Option<GameData>,                             // 等待下一個準備階段套用
+    config: ServerConfig,
+    seed: u64,
+    quiet: bool,
+    game_over: bool, // 只剩一位（或沒有）玩家存活
 }
 
 #[derive(Debug, PartialEq)]
@@ -19,7 +84,11 @@ pub enum Mode {
 }
 
 impl GameState {
-    pub fn new(websocket_player_channels: HashMap<String, WebsocketChannel>) -> Self {
+    pub fn new(
+        websocket_player_channels: HashMap<String, WebsocketChannel>,
+        game_data: GameData,
+        config: &ServerConfig,
+    ) -> Self {
         let mut world = World::new();
         
         // 註冊所有組件
@@ -29,22 +98,82 @@ impl GameState {
         world.register::<StatusEffects>();
         world.register::<TurnState>();
         world.register::<Player>();
+        world.register::<Owner>();
+        world.register::<Bench>();
+        world.register::<Equipment>();
+        world.register::<Team>();
+        world.register::<Creep>();
+        world.register::<Summoned>();
+        world.register::<Casting>();
+        world.register::<CurrentTarget>();
+        world.register::<Bot>();
     
         // 註冊 websocket_player_channels 作為全域變數
         world.insert(websocket_player_channels);
+        game_data.insert_into(&mut world);
+        world.insert(Synergies::default());
+        world.insert(PhaseTimer::default());
+        world.insert(ViewState::default());
+        world.insert(CommandQueue::default());
+        world.insert(Matchups::default());
+        world.insert(Carousel::default());
+        world.insert(config.clone());
+        world.insert(CombatLog::default());
+        world.insert(SimTime::default());
+        world.insert(ReplayRecorder::default());
+        world.insert(BattleStats::default());
+        world.insert(MatchHistory::default());
+        world.insert(Standings::default());
+        let seed = config.seed.unwrap_or_else(rand::random);
+        world.insert(SimRng::seeded(seed));
+        let mut input_log = InputLog::new(seed);
+        input_log.record_data(0, world.read_resource::<DataFingerprint>().0.clone());
+        world.insert(input_log);
     
         // 創建回合管理器（設置各階段時間）
-        let turn_manager = TurnManager::new(30.0, 60.0, 5.0);
+        let turn_manager = TurnManager::new(
+            config.preparation_time,
+            config.combat_time,
+            config.resolution_time,
+            config.carousel_pick_time * config.num_players as f32,
+        );
         
         GameState {
             world,
             turn_manager,
             mode: Mode::Selection,
-            mode_timer: 10.0, // 10 seconds for selection mode
+            mode_timer: config.selection_time,
+            data_reloads: None,
+            pending_data: None,
+            config: config.clone(),
+            seed,
+            quiet: false,
+            game_over: false,
         }
     }
 
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // 關閉回合流程的訊息，錯誤仍會輸出
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    // 目前的名次，遊戲結束後即為最終名次
+    pub fn placements(&self) -> Vec<Standing> {
+        let players = self.world.read_storage::<Player>();
+        let alive: Vec<(usize, i32)> = players.join().filter(|p| p.health > 0).map(|p| (p.id, p.health)).collect();
+        self.world.read_resource::<Standings>().placements(&alive)
+    }
+
     pub fn initialize_game(&mut self, num_players: usize) {
+        game_log!(self, "Simulation seed: {}", self.seed);
         // 創建回合狀態
         self.world
             .create_entity()
@@ -57,20 +186,20 @@ impl GameState {
             .build();
 
         use rand::seq::SliceRandom;
-        use rand::thread_rng;
 
         // 創建所有玩家並分配隨機棋子
-        let chess_types = vec![
-            ChessType::Warrior,
-            ChessType::Mage,
-            ChessType::Archer,
-            ChessType::Tank,
-        ];
+        let unit_ids: Vec<String> = self.world
+            .read_resource::<UnitDefinitions>()
+            .ids()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let bot_players = self.config.bot_players();
         for i in 0..num_players {
+            let bot = bot_players.iter().find(|(id, _)| *id == i).map(|(_, profile)| Bot::new(*profile, self.seed, i));
             self.world
                 .create_entity()
                 .with({
-                    let (tx, rx) = tokio::sync::mpsc::channel::<ChannelMessage>(32);
                     Player {
                         id: i,
                         name_id: format!("player_{}", i), // 為每個玩家生成唯一的名稱ID
@@ -78,122 +207,70 @@ impl GameState {
                         gold: 0,
                         level: 1,
                         experience: 0,
+                        shop: Vec::new(),
+                        items: Vec::new(),
+                        connected: bot.is_some(), // 電腦玩家一直在線
+                        abandoned: false,
                     }
                 })
+                .maybe_with(bot)
                 .build();
 
             // 隨機選擇一個棋子類型並生成棋子
-            if let Some(random_chess_type) = chess_types.choose(&mut thread_rng()).cloned() {
-                self.spawn_chess(random_chess_type, i as i32, 0); // 假設棋子初始位置為 (i, 0)
-            }
-        }
-    }
-
-    pub fn spawn_chess(&mut self, chess_type: ChessType, x: i32, y: i32) -> specs::Entity {
-        let (combat_stats) = match chess_type {
-            ChessType::Warrior => (
-                CombatStats {
-                    name: "Warrior".to_string(),
-                    hp: 100,
-                    max_hp: 100,
-                    attack: 15,
-                    defense: 10,
-                    magic_resist: 5,
-                    attack_speed: 1.0,
-                    attack_range: 1.0,    // 近戰攻擊距離
-                    mana: 0,
-                    max_mana: 100,
-                    skill: Skill {
-                        skill_type: SkillType::WhirlwindSlash,
-                        damage: 30,
-                        range: 2.0,
-                        duration: None,
-                        cooldown: 3,
-                        current_cooldown: 0,
-                    },
-                }
-            ),
-            ChessType::Mage => (
-                CombatStats {
-                    name: "Mage".to_string(),
-                    hp: 70,
-                    max_hp: 70,
-                    attack: 8,
-                    defense: 5,
-                    magic_resist: 15,
-                    attack_speed: 0.8,
-                    attack_range: 3.0,    // 法師遠程攻擊距離
-                    mana: 0,
-                    max_mana: 80,
-                    skill: Skill {
-                        skill_type: SkillType::Fireball,
-                        damage: 50,
-                        range: 4.0,
-                        duration: None,
-                        cooldown: 4,
-                        current_cooldown: 0,
-                    },
+            let random_unit_id = unit_ids.choose(&mut *self.world.write_resource::<SimRng>()).cloned();
+            if let Some(random_unit_id) = random_unit_id {
+                let role = self.world.read_resource::<UnitDefinitions>().get(&random_unit_id).map_or(Role::Melee, Role::of);
+                if let Some((x, y)) = formation::free_cell(role, &[]) {
+                    self.spawn_chess(&random_unit_id, i, x, y);
                 }
-            ),
-            ChessType::Archer => (
-                CombatStats {
-                    name: "Archer".to_string(),
-                    hp: 80,
-                    max_hp: 80,
-                    attack: 20,
-                    defense: 5,
-                    magic_resist: 5,
-                    attack_speed: 1.2,
-                    attack_range: 4.0,    // 弓箭手最遠攻擊距離
-                    mana: 0,
-                    max_mana: 90,
-                    skill: Skill {
-                        skill_type: SkillType::MultiShot,
-                        damage: 25,
-                        range: 3.0,
-                        duration: None,
-                        cooldown: 3,
-                        current_cooldown: 0,
-                    },
-                }
-            ),
-            ChessType::Tank => (
-                CombatStats {
-                    name: "Tank".to_string(),
-                    hp: 150,
-                    max_hp: 150,
-                    attack: 10,
-                    defense: 20,
-                    magic_resist: 20,
-                    attack_speed: 0.7,
-                    attack_range: 1.0,    // 近戰攻擊距離
-                    mana: 0,
-                    max_mana: 120,
-                    skill: Skill {
-                        skill_type: SkillType::ShieldBash,
-                        damage: 15,
-                        range: 1.5,
-                        duration: Some(2),
-                        cooldown: 5,
-                        current_cooldown: 0,
-                    },
+            }
+        }
+        self.start_preparation();
+    }
+
+    // 準備階段開始：存活的玩家獲得收入與經驗，並刷新商店
+    fn start_preparation(&mut self) {
+        let unit_definitions = self.world.read_resource::<UnitDefinitions>();
+        let mut rng = self.world.write_resource::<SimRng>();
+        let mut players = self.world.write_storage::<Player>();
+        for player in (&mut players).join().filter(|p| p.health > 0) {
+            player.gold += shop::round_income(player, &self.config);
+            shop::add_experience(player, shop::EXPERIENCE_PER_ROUND);
+            player.shop = shop::roll_shop(player.level, &unit_definitions, &mut rng);
+        }
+    }
+
+    // 依棋子定義生成棋子，找不到定義時回傳 None
+    pub fn spawn_chess(&mut self, unit_id: &str, owner: usize, x: i32, y: i32) -> Option<specs::Entity> {
+        let (name, combat_stats) = {
+            let unit_definitions = self.world.read_resource::<UnitDefinitions>();
+            let definition = match unit_definitions.get(unit_id) {
+                Some(definition) => definition,
+                None => {
+                    log::error!("Unknown unit id: {}", unit_id);
+                    return None;
                 }
-            ),
+            };
+            (definition.name.clone(), definition.combat_stats(1))
         };
 
-        println!("Chess entity created with base stats: {:?}", combat_stats);
-        self.world
+        game_log!(self, "Chess entity created with base stats: {:?}", combat_stats);
+        let id = self.world.write_resource::<SimRng>().uuid();
+        let entity = self.world
             .create_entity()
             .with(Chess {
-                id: Uuid::new_v4(),
-                name: format!("{:?}", chess_type),
+                id,
+                name,
                 level: 1,
-                chess_type,
+                unit_id: unit_id.to_string(),
             })
+            .with(Owner { player_id: owner })
             .with(Position { x, y })
             .with(combat_stats)
             .with(StatusEffects { effects: Vec::new() })
-            .build()
+            .with(Equipment::default())
+            .build();
+        Some(entity)
     }
 
     pub fn remove_chess(&mut self, entity: specs::Entity) {
@@ -202,19 +279,44 @@ impl GameState {
         }
     }
 
-    pub fn update(&mut self, delta_time: f32) {
+    // 接收熱更新的遊戲資料，於每個準備階段開始時套用
+    pub fn watch_game_data(&mut self, data_reloads: tokio::sync::mpsc::Receiver<GameData>) {
+        self.data_reloads = Some(data_reloads);
+    }
+
+    // 推進一個固定 tick；模擬只依 tick 數前進，與實際經過的時間無關
+    pub fn update(&mut self) {
+        let delta_time = self.config.tick_seconds();
+        {
+            let mut sim_time = self.world.write_resource::<SimTime>();
+            sim_time.tick += 1;
+            sim_time.delta = delta_time;
+        }
+
         // Update mode timer
         self.mode_timer -= delta_time;
-    
-    
+
+        if let Some(data_reloads) = self.data_reloads.as_mut() {
+            while let Ok(game_data) = data_reloads.try_recv() {
+                self.pending_data = Some(game_data);
+            }
+        }
+        let mut entered_preparation = false;
+        let mut entered_combat = false;
+        let mut entered_resolution = false;
+        let mut entered_carousel = false;
+
+        if self.current_phase() == Some(TurnPhase::Carousel) {
+            self.update_carousel(delta_time);
+        }
+        let carousel_finished = self.world.read_resource::<Carousel>().is_finished();
+
         match self.mode {
             Mode::Selection => {
                 if self.mode_timer <= 0.0 {
                     self.mode = Mode::Combat;
                     self.mode_timer = 0.0; // Reset timer
-                    println!("Transitioning to Combat mode...");
-                    // Start pairing players for combat
-                    self.pair_players_for_combat();
+                    game_log!(self, "Transitioning to Combat mode...");
                 }
             }
             Mode::Combat => {
@@ -222,31 +324,670 @@ impl GameState {
                 let mut turn_states = self.world.write_storage::<TurnState>();
                 if let Some(turn_state) = (&mut turn_states).join().next() {
                     // 更新回合管理器，檢查是否需要進入下一階段
-                    if self.turn_manager.update(delta_time, turn_state) {
+                    let mut phase_over = self.turn_manager.update(delta_time, turn_state);
+                    // 所有人都選完時提早結束輪抽
+                    if turn_state.current_phase == TurnPhase::Carousel && carousel_finished {
+                        self.turn_manager.reset();
+                        phase_over = true;
+                    }
+                    if phase_over {
                         // 當前階段結束，進入下一階段
                         match turn_state.current_phase {
                             TurnPhase::Preparation => {
                                 turn_state.current_phase = TurnPhase::Combat;
+                                entered_combat = true;
                             }
                             TurnPhase::Combat => {
                                 turn_state.current_phase = TurnPhase::Resolution;
+                                entered_resolution = true;
                             }
                             TurnPhase::Resolution => {
-                                turn_state.current_phase = TurnPhase::Preparation;
                                 turn_state.current_player = (turn_state.current_player + 1) % turn_state.total_players;
-                                if turn_state.current_player == 0 {
-                                    turn_state.turn_number += 1;
+                                // 每經過一次戰鬥就進入下一回合，PvE 與輪抽回合依此編號判斷
+                                turn_state.turn_number += 1;
+                                if self.config.is_carousel_round(turn_state.turn_number) {
+                                    turn_state.current_phase = TurnPhase::Carousel;
+                                    entered_carousel = true;
+                                } else {
+                                    turn_state.current_phase = TurnPhase::Preparation;
+                                    entered_preparation = true;
                                 }
                             }
+                            TurnPhase::Carousel => {
+                                turn_state.current_phase = TurnPhase::Preparation;
+                                entered_preparation = true;
+                            }
                         }
                     }
                 }
             }
         }
+
+        if entered_carousel {
+            self.start_carousel();
+        }
+        if entered_preparation {
+            self.world.insert(Carousel::default());
+            self.apply_pending_data();
+            self.start_preparation();
+        }
+        if entered_combat {
+            self.start_combat();
+        }
+        if entered_resolution {
+            self.resolve_combat();
+            self.drop_items();
+        }
+
+        self.update_phase_timer();
     }
     
-    fn pair_players_for_combat(&self) {
-        println!("Pairing players for combat...");
-        // Implement pairing logic here
+    // 套用熱更新的遊戲資料：記錄差異，並以新數值重設場上與備戰席的棋子
+    fn apply_pending_data(&mut self) {
+        let game_data = match self.pending_data.take() {
+            Some(game_data) => game_data,
+            None => return,
+        };
+
+        let current = GameData {
+            units: (*self.world.read_resource::<UnitDefinitions>()).clone(),
+            traits: (*self.world.read_resource::<TraitDefinitions>()).clone(),
+            items: (*self.world.read_resource::<ItemDefinitions>()).clone(),
+            creeps: (*self.world.read_resource::<CreepBoards>()).clone(),
+        };
+        let changes = current.diff(&game_data);
+        if changes.is_empty() {
+            game_log!(self, "遊戲資料重新載入，沒有變動");
+            return;
+        }
+        game_log!(self, "套用遊戲資料變動（{} 項）:", changes.len());
+        for change in &changes {
+            game_log!(self, "  {}", change);
+        }
+
+        {
+            let chess = self.world.read_storage::<Chess>();
+            let mut combat_stats = self.world.write_storage::<CombatStats>();
+            for (chess, stats) in (&chess, &mut combat_stats).join() {
+                match game_data.units.get(&chess.unit_id) {
+                    Some(definition) => *stats = definition.combat_stats(chess.level),
+                    None => log::warn!("Unit {} was removed from data, keeping old stats", chess.unit_id),
+                }
+            }
+        }
+        game_data.insert_into(&mut self.world);
+        // 之後的對局需要這份資料才能重現
+        let tick = self.world.read_resource::<SimTime>().tick;
+        let fingerprint = self.world.read_resource::<DataFingerprint>().0.clone();
+        game_log!(self, "遊戲資料指紋: {}", fingerprint);
+        self.world.write_resource::<InputLog>().record_data(tick, fingerprint);
+    }
+
+    // 戰鬥開始：統計每位玩家場上的羈絆，並以基礎屬性加上羈絆與道具加成重設場上的棋子
+    fn start_combat(&mut self) {
+        self.eliminate_abandoned();
+        self.fill_boards();
+        let synergies = prepare_combat_stats(&self.world);
+        self.world.insert(synergies);
+        self.world.write_storage::<Casting>().clear();
+        self.world.write_storage::<CurrentTarget>().clear();
+        self.pair_players_for_combat();
+        let starting_units = replay::starting_units(&self.world);
+        self.world.write_resource::<BattleStats>().begin(&starting_units);
+        self.begin_replays(starting_units);
+    }
+
+    // 斷線超過寬限時間的玩家不再參與配對，以 0 血淘汰
+    fn eliminate_abandoned(&mut self) {
+        let round = {
+            let turn_states = self.world.read_storage::<TurnState>();
+            (&turn_states).join().next().map_or(1, |turn_state| turn_state.turn_number)
+        };
+        let mut eliminated = Vec::new();
+        {
+            let mut players = self.world.write_storage::<Player>();
+            let mut standings = self.world.write_resource::<Standings>();
+            for player in (&mut players).join().filter(|p| p.abandoned && p.health > 0) {
+                player.health = 0;
+                standings.eliminate(player.id, round, player.health);
+                eliminated.push(player.name_id.clone());
+            }
+        }
+        for name_id in eliminated {
+            game_log!(self, "Player {} abandoned the game and has been eliminated", name_id);
+        }
+        self.check_game_over();
+    }
+
+    // 場上未滿而備戰席還有棋子的玩家，自動讓備戰席中價值最高的棋子上場
+    fn fill_boards(&mut self) {
+        let entities = self.world.entities();
+        let chess = self.world.read_storage::<Chess>();
+        let owners = self.world.read_storage::<Owner>();
+        let mut positions = self.world.write_storage::<Position>();
+        let mut benches = self.world.write_storage::<Bench>();
+        let players = self.world.read_storage::<Player>();
+        let unit_definitions = self.world.read_resource::<UnitDefinitions>();
+        for player in players.join().filter(|p| p.health > 0) {
+            let units = formation::owned_units(player.id, &entities, &chess, &owners, &positions, &benches, &unit_definitions);
+            let moves = formation::fill(&units, player.level as usize);
+            if !moves.is_empty() {
+                game_log!(self, "Player {} left units on the bench, placed {} automatically", player.name_id, moves.len());
+                formation::apply(&moves, &entities, &chess, &mut positions, &mut benches);
+            }
+        }
+    }
+
+    // 為每個有戰鬥的場地記錄開場陣容，之後由 ReplaySystem 寫入事件
+    fn begin_replays(&mut self, starting_units: Vec<(usize, ReplayUnit)>) {
+        let replays = {
+            let matchups = self.world.read_resource::<Matchups>();
+            let seed = self.world.read_resource::<SimRng>().seed();
+            let data = self.world.read_resource::<DataFingerprint>().0.clone();
+            let start_tick = self.world.read_resource::<SimTime>().tick;
+            let mut replays: Vec<CombatReplay> = Vec::new();
+            for matchup in matchups.matchups.iter().filter(|m| m.opponent != Opponent::Bye) {
+                let index = match replays.iter().position(|r| r.arena == matchup.arena) {
+                    Some(index) => index,
+                    None => {
+                        replays.push(CombatReplay {
+                            version: REPLAY_VERSION,
+                            seed,
+                            data: data.clone(),
+                            round: matchups.round,
+                            arena: matchup.arena,
+                            tick_rate: self.config.tick_rate,
+                            start_tick,
+                            end_tick: start_tick,
+                            sides: Vec::new(),
+                            units: Vec::new(),
+                            frames: Vec::new(),
+                        });
+                        replays.len() - 1
+                    }
+                };
+                let sides = &mut replays[index].sides;
+                sides.push(ReplaySide { side: matchup.side, player_id: Some(matchup.player_id), board: None });
+                if let Opponent::Creeps { board } = &matchup.opponent {
+                    sides.push(ReplaySide { side: 1, player_id: None, board: Some(board.clone()) });
+                }
+            }
+
+            for (arena, unit) in starting_units {
+                if let Some(replay) = replays.iter_mut().find(|r| r.arena == arena) {
+                    replay.units.push(unit);
+                }
+            }
+            replays
+        };
+        self.world.write_resource::<ReplayRecorder>().begin(replays);
+    }
+
+    // 戰鬥結束：保存本回合的重播，有設定 replay_dir 時寫入檔案
+    fn finish_replays(&mut self) {
+        let end_tick = self.world.read_resource::<SimTime>().tick;
+        let replays = self.world.write_resource::<ReplayRecorder>().finish(end_tick);
+        let dir = match &self.config.replay_dir {
+            Some(dir) => std::path::Path::new(dir),
+            None => return,
+        };
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("Failed to create replay directory {}: {}", dir.display(), e);
+            return;
+        }
+        for replay in &replays {
+            let path = dir.join(replay.file_name());
+            if let Err(e) = replay.save(&path) {
+                eprintln!("Failed to save replay {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    // 結算時每位仍在遊戲中的玩家有機率獲得一個基礎道具，依掉落權重抽選
+    fn drop_items(&mut self) {
+        use rand::Rng;
+        use rand::seq::SliceRandom;
+
+        let item_definitions = self.world.read_resource::<ItemDefinitions>();
+        let drop_table = item_definitions.drop_table();
+        if drop_table.is_empty() {
+            return;
+        }
+        let mut rng = self.world.write_resource::<SimRng>();
+        let mut players = self.world.write_storage::<Player>();
+        for player in (&mut players).join().filter(|p| p.health > 0) {
+            if !rng.gen_bool(self.config.item_drop_chance as f64) {
+                continue;
+            }
+            if let Ok((item_id, _)) = drop_table.choose_weighted(&mut *rng, |(_, weight)| *weight) {
+                game_log!(self, "Player {} received item {}", player.name_id, item_id);
+                player.items.push(item_id.to_string());
+            }
+        }
+    }
+
+    fn current_phase(&self) -> Option<TurnPhase> {
+        let turn_states = self.world.read_storage::<TurnState>();
+        (&turn_states).join().next().map(|turn_state| turn_state.current_phase)
+    }
+
+    // 輪抽開始：準備與存活玩家人數相同的棋子，每個帶一個隨機基礎道具，血量低的玩家先選
+    fn start_carousel(&mut self) {
+        use rand::seq::SliceRandom;
+
+        let mut order: Vec<(i32, usize)> = {
+            let players = self.world.read_storage::<Player>();
+            (&players).join().filter(|p| p.health > 0).map(|p| (p.health, p.id)).collect()
+        };
+        order.sort();
+        let order: Vec<usize> = order.into_iter().map(|(_, id)| id).collect();
+
+        let offers: Vec<CarouselOffer> = {
+            let unit_definitions = self.world.read_resource::<UnitDefinitions>();
+            let item_definitions = self.world.read_resource::<ItemDefinitions>();
+            let unit_ids = unit_definitions.ids();
+            let drop_table = item_definitions.drop_table();
+            let mut rng = self.world.write_resource::<SimRng>();
+            let mut offers = Vec::new();
+            for _ in 0..order.len() {
+                let unit_id = match unit_ids.choose(&mut *rng) {
+                    Some(unit_id) => unit_id.to_string(),
+                    None => break,
+                };
+                let item = drop_table
+                    .choose_weighted(&mut *rng, |(_, weight)| *weight)
+                    .ok()
+                    .map(|(item_id, _)| item_id.to_string());
+                offers.push(CarouselOffer { unit_id, item, picked_by: None });
+            }
+            offers
+        };
+
+        game_log!(self, "Carousel started, pick order {:?}, offers {:?}", order, offers);
+        self.world.insert(Carousel::new(offers, order, self.config.carousel_pick_time));
+    }
+
+    // 輪抽計時：逾時或離線的玩家自動分配，並把已選的棋子放到玩家的備戰席
+    fn update_carousel(&mut self, delta_time: f32) {
+        let pick_time = self.config.carousel_pick_time;
+        let mut assigned = Vec::new();
+        let picks = {
+            let players = self.world.read_storage::<Player>();
+            let mut carousel = self.world.write_resource::<Carousel>();
+            carousel.pick_remaining -= delta_time;
+            while let Some(player_id) = carousel.picking() {
+                let connected = (&players).join().any(|p| p.id == player_id && p.connected);
+                if connected && carousel.pick_remaining > 0.0 {
+                    break;
+                }
+                assigned.extend(carousel.auto_pick(pick_time));
+            }
+            carousel.take_picks()
+        };
+
+        for (player_id, offer) in assigned {
+            game_log!(self, "Player {} ran out of time, assigned carousel offer {}", player_id, offer);
+        }
+        for (player_id, offer) in picks {
+            game_log!(self, "Player {} picked {} with item {:?} from the carousel", player_id, offer.unit_id, offer.item);
+            let items: Vec<String> = offer.item.into_iter().collect();
+            if self.spawn_benched(&offer.unit_id, player_id, items.clone()).is_none() {
+                // 備戰席已滿時只拿到道具
+                let mut players = self.world.write_storage::<Player>();
+                if let Some(player) = (&mut players).join().find(|p| p.id == player_id) {
+                    player.items.extend(items);
+                }
+            }
+        }
+    }
+
+    // 在玩家備戰席的第一個空位生成 1 星棋子，備戰席已滿或找不到定義時回傳 None
+    pub fn spawn_benched(&mut self, unit_id: &str, owner: usize, items: Vec<String>) -> Option<specs::Entity> {
+        let slot = {
+            let owners = self.world.read_storage::<Owner>();
+            let benches = self.world.read_storage::<Bench>();
+            let used: Vec<usize> = (&owners, &benches)
+                .join()
+                .filter(|(o, _)| o.player_id == owner)
+                .map(|(_, b)| b.slot)
+                .collect();
+            match (0..BENCH_SIZE).find(|slot| !used.contains(slot)) {
+                Some(slot) => slot,
+                None => {
+                    game_log!(self, "Player {}'s bench is full, cannot add {}", owner, unit_id);
+                    return None;
+                }
+            }
+        };
+        let (name, combat_stats) = {
+            let unit_definitions = self.world.read_resource::<UnitDefinitions>();
+            let definition = match unit_definitions.get(unit_id) {
+                Some(definition) => definition,
+                None => {
+                    log::error!("Unknown unit id: {}", unit_id);
+                    return None;
+                }
+            };
+            (definition.name.clone(), definition.combat_stats(1))
+        };
+
+        let id = self.world.write_resource::<SimRng>().uuid();
+        let entity = self.world
+            .create_entity()
+            .with(Chess {
+                id,
+                name,
+                level: 1,
+                unit_id: unit_id.to_string(),
+            })
+            .with(Owner { player_id: owner })
+            .with(Bench { slot })
+            .with(combat_stats)
+            .with(StatusEffects { effects: Vec::new() })
+            .with(Equipment { items })
+            .build();
+        Some(entity)
+    }
+
+    // 將目前階段剩餘時間寫入 world，供 ViewSystem 送給客戶端
+    fn update_phase_timer(&mut self) {
+        let remaining_time = {
+            let turn_states = self.world.read_storage::<TurnState>();
+            match (&turn_states).join().next() {
+                Some(turn_state) => self.turn_manager.get_remaining_time(turn_state),
+                None => return,
+            }
+        };
+        self.world.write_resource::<PhaseTimer>().remaining_time = remaining_time;
     }
-} 
\ No newline at end of file
+
+    // 產生本回合的對戰組合：PvE 回合每位玩家各自面對野怪陣容，否則隨機兩兩配對，
+    // 並為參戰的棋子標上場地與陣營，把位置換成場地座標
+    fn pair_players_for_combat(&mut self) {
+        use rand::seq::SliceRandom;
+
+        let round = {
+            let turn_states = self.world.read_storage::<TurnState>();
+            (&turn_states).join().next().map_or(1, |turn_state| turn_state.turn_number)
+        };
+        let mut alive: Vec<usize> = {
+            let players = self.world.read_storage::<Player>();
+            let mut alive: Vec<usize> = (&players).join().filter(|p| p.health > 0).map(|p| p.id).collect();
+            alive.sort();
+            alive
+        };
+
+        let board = if self.config.is_pve_round(round) {
+            let board = self.world.read_resource::<CreepBoards>().for_round(round).cloned();
+            if board.is_none() {
+                log::warn!("Round {} is a PvE round but no creep board applies, pairing players instead", round);
+            }
+            board
+        } else {
+            None
+        };
+
+        let mut matchups: Vec<Matchup> = Vec::new();
+        match &board {
+            Some(board) => {
+                for (arena, &player_id) in alive.iter().enumerate() {
+                    matchups.push(Matchup {
+                        arena,
+                        side: 0,
+                        player_id,
+                        opponent: Opponent::Creeps { board: board.id.clone() },
+                        result: None,
+                    });
+                }
+            }
+            None => {
+                alive.shuffle(&mut *self.world.write_resource::<SimRng>());
+                for (arena, pair) in alive.chunks(2).enumerate() {
+                    match *pair {
+                        [home, away] => {
+                            matchups.push(Matchup { arena, side: 0, player_id: home, opponent: Opponent::Player { player_id: away }, result: None });
+                            matchups.push(Matchup { arena, side: 1, player_id: away, opponent: Opponent::Player { player_id: home }, result: None });
+                        }
+                        [player_id] => {
+                            matchups.push(Matchup { arena, side: 0, player_id, opponent: Opponent::Bye, result: None });
+                        }
+                        _ => {}
+                    }
+                }
+                matchups.sort_by_key(|m| m.player_id);
+            }
+        }
+
+        {
+            let entities = self.world.entities();
+            let owners = self.world.read_storage::<Owner>();
+            let mut positions = self.world.write_storage::<Position>();
+            let mut teams = self.world.write_storage::<Team>();
+            for (entity, owner, position) in (&entities, &owners, &mut positions).join() {
+                let matchup = matchups
+                    .iter()
+                    .find(|m| m.player_id == owner.player_id && m.opponent != Opponent::Bye);
+                if let Some(matchup) = matchup {
+                    let _ = teams.insert(entity, Team { arena: matchup.arena, side: matchup.side });
+                    (position.x, position.y) = arena_position(matchup.side, (position.x, position.y));
+                }
+            }
+        }
+
+        if let Some(board) = &board {
+            for matchup in &matchups {
+                for unit in &board.units {
+                    self.spawn_creep(&unit.unit_id, unit.level, unit.position, matchup.arena);
+                }
+            }
+        }
+
+        game_log!(self, "Round {} matchups: {:?}", round, matchups);
+        self.world.insert(Matchups { round, matchups });
+    }
+
+    // 生成一隻野怪，屬於該場地的對手陣營
+    fn spawn_creep(&mut self, unit_id: &str, level: u32, position: (i32, i32), arena: usize) {
+        let (name, combat_stats) = {
+            let unit_definitions = self.world.read_resource::<UnitDefinitions>();
+            match unit_definitions.get(unit_id) {
+                Some(definition) => (
+                    definition.name.clone(),
+                    stats::effective_stats(definition, level, &Default::default()),
+                ),
+                None => {
+                    log::error!("Unknown creep unit id: {}", unit_id);
+                    return;
+                }
+            }
+        };
+
+        let id = self.world.write_resource::<SimRng>().uuid();
+        let (x, y) = arena_position(1, position);
+        self.world
+            .create_entity()
+            .with(Chess {
+                id,
+                name,
+                level,
+                unit_id: unit_id.to_string(),
+            })
+            .with(Position { x, y })
+            .with(combat_stats)
+            .with(StatusEffects { effects: Vec::new() })
+            .with(Team { arena, side: 1 })
+            .with(Creep)
+            .build();
+    }
+
+    // 結算每場對戰：一方全滅則另一方獲勝，時間到雙方都有存活時雙方皆判負。
+    // PvP 落敗扣基礎傷害加對手存活棋子星級；PvE 獲勝得到獎勵，落敗依野怪陣容扣血
+    fn resolve_combat(&mut self) {
+        use rand::seq::SliceRandom;
+
+        self.finish_replays();
+
+        // (場地, 陣營) -> (存活數, 存活星級總和)
+        let mut survivors: HashMap<(usize, usize), (usize, u32)> = HashMap::new();
+        {
+            let chess = self.world.read_storage::<Chess>();
+            let teams = self.world.read_storage::<Team>();
+            let combat_stats = self.world.read_storage::<CombatStats>();
+            for (chess, team, stats) in (&chess, &teams, &combat_stats).join() {
+                if stats.hp > 0 {
+                    let entry = survivors.entry((team.arena, team.side)).or_default();
+                    entry.0 += 1;
+                    entry.1 += chess.level;
+                }
+            }
+        }
+
+        let mut matchups = std::mem::take(&mut *self.world.write_resource::<Matchups>());
+        {
+            let creep_boards = self.world.read_resource::<CreepBoards>();
+            let item_definitions = self.world.read_resource::<ItemDefinitions>();
+            let drop_table = item_definitions.drop_table();
+            let mut players = self.world.write_storage::<Player>();
+            let mut rng = self.world.write_resource::<SimRng>();
+            let battle_stats = self.world.read_resource::<BattleStats>();
+            let mut standings = self.world.write_resource::<Standings>();
+
+            for matchup in matchups.matchups.iter_mut() {
+                let player = match (&mut players).join().find(|p| p.id == matchup.player_id) {
+                    Some(player) => player,
+                    None => continue,
+                };
+                if matchup.opponent == Opponent::Bye {
+                    continue;
+                }
+                let side = matchup.side;
+                let (alive, _) = survivors.get(&(matchup.arena, side)).copied().unwrap_or_default();
+                let (enemy_alive, enemy_levels) = survivors.get(&(matchup.arena, 1 - side)).copied().unwrap_or_default();
+                let won = alive > 0 && enemy_alive == 0;
+                let timed_out = alive > 0 && enemy_alive > 0;
+
+                let mut result = MatchupResult {
+                    won,
+                    damage: 0,
+                    gold: 0,
+                    items: Vec::new(),
+                    timed_out,
+                    meter: battle_stats.arena(matchup.arena),
+                };
+                match &matchup.opponent {
+                    Opponent::Creeps { board } => match creep_boards.get(board) {
+                        Some(board) if won => {
+                            result.gold = board.reward.gold;
+                            result.items = board.reward.items.clone();
+                            for _ in 0..board.reward.random_items {
+                                if let Ok((item_id, _)) = drop_table.choose_weighted(&mut *rng, |(_, weight)| *weight) {
+                                    result.items.push(item_id.to_string());
+                                }
+                            }
+                        }
+                        Some(board) => result.damage = board.loss_damage,
+                        None => {}
+                    },
+                    _ if !won => result.damage = self.config.pvp_base_damage + enemy_levels as i32,
+                    _ => {}
+                }
+
+                player.health -= result.damage;
+                player.gold += result.gold;
+                player.items.extend(result.items.iter().cloned());
+                game_log!(
+                    self,
+                    "Player {} {} round {} vs {:?}: -{} hp, +{} gold, items {:?}",
+                    player.name_id,
+                    if won { "won" } else { "lost" },
+                    matchups.round,
+                    matchup.opponent,
+                    result.damage,
+                    result.gold,
+                    result.items
+                );
+                if player.health <= 0 {
+                    game_log!(self, "Player {} has been eliminated", player.name_id);
+                    standings.eliminate(player.id, matchups.round, player.health);
+                }
+                matchup.result = Some(result);
+            }
+        }
+        self.world.write_resource::<MatchHistory>().record(&matchups);
+        self.world.insert(matchups);
+        self.check_game_over();
+
+        // 移除野怪、召喚物與本回合的對戰標記
+        {
+            let entities = self.world.entities();
+            let creeps = self.world.read_storage::<Creep>();
+            let summoned = self.world.read_storage::<Summoned>();
+            for (entity, _) in (&entities, creeps.mask() | summoned.mask()).join() {
+                let _ = entities.delete(entity);
+            }
+        }
+        self.world.maintain();
+        {
+            let teams = self.world.read_storage::<Team>();
+            let mut positions = self.world.write_storage::<Position>();
+            for (team, position) in (&teams, &mut positions).join() {
+                (position.x, position.y) = board_position(team.side, (position.x, position.y));
+            }
+        }
+        self.world.write_storage::<Team>().clear();
+        self.world.write_storage::<Casting>().clear();
+        self.world.write_storage::<CurrentTarget>().clear();
+    }
+
+    // 只剩一位玩家存活（單人遊戲時該玩家被淘汰）即結束遊戲，並輸出最終名次
+    fn check_game_over(&mut self) {
+        if self.game_over {
+            return;
+        }
+        let alive = self.world.read_storage::<Player>().join().filter(|p| p.health > 0).count();
+        if alive > 1 || (alive == 1 && self.config.num_players == 1) {
+            return;
+        }
+        self.game_over = true;
+        game_log!(self, "Game over, final standings:");
+        for standing in self.placements() {
+            game_log!(self, "  {}. player_{} ({} hp, eliminated round {:?})", standing.place, standing.player_id, standing.health, standing.eliminated_round);
+        }
+    }
+}
+
+// 以基礎屬性加上羈絆與道具加成重設場上（有 Position）的棋子，魔力與冷卻歸零，回傳各玩家的羈絆。
+// 伺服器與無網路的戰鬥模擬共用
+pub fn prepare_combat_stats(world: &World) -> Synergies {
+    let unit_definitions = world.read_resource::<UnitDefinitions>();
+    let trait_definitions = world.read_resource::<TraitDefinitions>();
+    let item_definitions = world.read_resource::<ItemDefinitions>();
+    let chess = world.read_storage::<Chess>();
+    let owners = world.read_storage::<Owner>();
+    let positions = world.read_storage::<Position>();
+    let equipment = world.read_storage::<Equipment>();
+    let mut combat_stats = world.write_storage::<CombatStats>();
+    let mut status_effects = world.write_storage::<StatusEffects>();
+
+    let synergies = synergy::count_traits(&chess, &owners, &positions, &unit_definitions, &trait_definitions);
+
+    for (chess, owner, _, equipment, stats, effects) in (&chess, &owners, &positions, equipment.maybe(), &mut combat_stats, &mut status_effects).join() {
+        // 每場戰鬥開始時魔力與技能冷卻歸零
+        stats.mana = 0;
+        stats.skill.current_cooldown = 0.0;
+        let definition = match unit_definitions.get(&chess.unit_id) {
+            Some(definition) => definition,
+            None => continue,
+        };
+        let active_traits = synergies.for_player(owner.player_id);
+        let (mut modifiers, mut bonus_effects) = synergy::trait_bonuses(&active_traits, definition, &trait_definitions);
+        if let Some(equipment) = equipment {
+            let (item_modifiers, item_effects) = item_definitions.bonuses(&equipment.items);
+            modifiers.add(&item_modifiers);
+            bonus_effects.extend(item_effects);
+        }
+        *stats = stats::effective_stats(definition, chess.level, &modifiers);
+        effects.effects = bonus_effects;
+    }
+    synergies
+}
@@ -0,0 +1,216 @@
+use serde::Serialize;
+use specs::{DispatcherBuilder, Join, WorldExt};
+use std::collections::HashMap;
+use std::path::Path;
+use crate::{Chess, Owner};
+use crate::battle_stats::BattleStatsSystem;
+use crate::bots::{Bot, BotSystem};
+use crate::combat::CombatSystem;
+use crate::commands::{CommandSystem, InputLog, InputReplay, InputReplaySystem};
+use crate::config::ServerConfig;
+use crate::data::{DataError, GameData};
+use crate::game_state::{GameState, SimTime};
+use crate::players_system::PlayersSystem;
+use crate::replay::ReplaySystem;
+use crate::rounds::{MatchHistory, Opponent};
+use crate::skills::SkillRegistry;
+use crate::turn::{Player, TurnState};
+use crate::WebsocketChannel;
+
+// 玩家沒有指定難度時使用的電腦玩家
+pub const DEFAULT_BOT: &str = "normal";
+
+// 一位玩家在對局結束時的名次與狀態
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerSummary {
+    pub place: usize,
+    pub player_id: usize,
+    pub profile: String,
+    pub eliminated_round: Option<u32>,
+    pub health: i32,
+    pub level: u32,
+    pub gold: i32,
+    pub units: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchSummary {
+    pub seed: u64,
+    pub rounds: u32,
+    pub ticks: u64,
+    pub sim_secs: f32,
+    pub winner: Option<usize>,
+    pub combats: usize,           // 實際進行的戰鬥（不含輪空）
+    pub timed_out_combats: usize, // 時間到仍未分出勝負的戰鬥
+    pub placements: Vec<PlayerSummary>,
+}
+
+#[derive(Debug)]
+pub enum MatchError {
+    RoundLimit { seed: u64, rounds: u32, ticks: u64 }, // 超過回合上限仍未結束
+    DataMismatch { seed: u64, expected: String, found: Vec<String> }, // 輸入紀錄使用的遊戲資料與目前的不同
+}
+
+impl std::fmt::Display for MatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MatchError::RoundLimit { seed, rounds, ticks } => {
+                write!(f, "seed {}: match did not finish within {} rounds ({} ticks)", seed, rounds, ticks)
+            }
+            MatchError::DataMismatch { seed, expected, found } => write!(
+                f,
+                "seed {}: input log was recorded with game data {:?}, loaded data is {}",
+                seed, found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MatchError {}
+
+// 只有電腦玩家的完整對局：不連網、不睡眠，以固定 tick 從開局一路執行到遊戲結束
+#[derive(Clone)]
+pub struct BotMatch {
+    data: GameData,
+    skills: SkillRegistry,
+    config: ServerConfig,
+}
+
+impl BotMatch {
+    // 沒有指定難度的玩家以 DEFAULT_BOT 補滿，所有玩家都由電腦控制
+    pub fn new(data: GameData, skills: SkillRegistry, mut config: ServerConfig) -> Self {
+        let missing = config.num_players.saturating_sub(config.bots.len());
+        config.bots.extend(std::iter::repeat(DEFAULT_BOT.to_string()).take(missing));
+        BotMatch { data, skills, config }
+    }
+
+    pub fn load(data_dir: &Path, config: ServerConfig) -> Result<Self, DataError> {
+        let skills = SkillRegistry::builtin();
+        let data = GameData::load(data_dir, &skills)?;
+        Ok(BotMatch::new(data, skills, config))
+    }
+
+    pub fn data(&self) -> &GameData {
+        &self.data
+    }
+
+    // 相同的種子與設定會得到相同的對局；超過 max_rounds 回合仍未結束時回傳錯誤
+    pub fn run(&self, seed: u64, max_rounds: u32) -> Result<MatchSummary, MatchError> {
+        self.play(seed, None, max_rounds).map(|(summary, _)| summary)
+    }
+
+    // 同 run，另外回傳對局中所有玩家的輸入紀錄
+    pub fn run_recorded(&self, seed: u64, max_rounds: u32) -> Result<(MatchSummary, InputLog), MatchError> {
+        self.play(seed, None, max_rounds)
+    }
+
+    // 以紀錄中的種子與輸入重現對局：電腦玩家不再自行決策，改由紀錄在原本的 tick 送出相同的指令。
+    // 同時回傳重現時再次記錄的輸入，應與原本的紀錄相同。紀錄中途熱更新過資料，
+    // 或使用的資料與載入的不同時無法重現，回傳 DataMismatch
+    pub fn replay(&self, inputs: &InputLog, max_rounds: u32) -> Result<(MatchSummary, InputLog), MatchError> {
+        let expected = self.data.fingerprint();
+        let found: Vec<String> = inputs.data.iter().map(|record| record.fingerprint.clone()).collect();
+        if found != [expected.clone()] {
+            return Err(MatchError::DataMismatch { seed: inputs.seed, expected, found });
+        }
+        self.play(inputs.seed, Some(inputs), max_rounds)
+    }
+
+    fn play(&self, seed: u64, inputs: Option<&InputLog>, max_rounds: u32) -> Result<(MatchSummary, InputLog), MatchError> {
+        let mut config = self.config.clone();
+        config.seed = Some(seed);
+        config.replay_dir = None;
+
+        let mut game_state = GameState::new(HashMap::<String, WebsocketChannel>::new(), self.data.clone(), &config);
+        game_state.set_quiet(true);
+        game_state.world.insert(self.skills.clone());
+        game_state.initialize_game(config.num_players);
+
+        // 和伺服器相同的系統，但沒有畫面同步與觀戰；重現時以輸入紀錄取代電腦玩家
+        let builder = DispatcherBuilder::new()
+            .with(CombatSystem, "combat_system", &[])
+            .with(ReplaySystem, "replay_system", &["combat_system"])
+            .with(BattleStatsSystem, "battle_stats_system", &["combat_system"])
+            .with(PlayersSystem, "players_system", &["combat_system"]);
+        let builder = match inputs {
+            Some(inputs) => {
+                game_state.world.insert(InputReplay::new(inputs));
+                builder.with(InputReplaySystem, "input_system", &["combat_system"])
+            }
+            None => builder.with(BotSystem, "input_system", &["combat_system"]),
+        };
+        let mut dispatcher = builder
+            .with(CommandSystem, "command_system", &["players_system", "input_system"])
+            .build();
+        dispatcher.setup(&mut game_state.world);
+
+        while !game_state.is_game_over() {
+            let round = current_round(&game_state);
+            if round > max_rounds {
+                let ticks = game_state.world.read_resource::<SimTime>().tick;
+                return Err(MatchError::RoundLimit { seed, rounds: max_rounds, ticks });
+            }
+            game_state.update();
+            dispatcher.dispatch(&mut game_state.world);
+            game_state.world.maintain();
+        }
+
+        let inputs = (*game_state.world.read_resource::<InputLog>()).clone();
+        Ok((summarize(&game_state, &config, seed), inputs))
+    }
+}
+
+fn current_round(game_state: &GameState) -> u32 {
+    let turn_states = game_state.world.read_storage::<TurnState>();
+    turn_states.join().next().map_or(0, |turn_state| turn_state.turn_number)
+}
+
+fn summarize(game_state: &GameState, config: &ServerConfig, seed: u64) -> MatchSummary {
+    let world = &game_state.world;
+    let ticks = world.read_resource::<SimTime>().tick;
+    let history = world.read_resource::<MatchHistory>();
+    let players = world.read_storage::<Player>();
+    let bots = world.read_storage::<Bot>();
+    let chess = world.read_storage::<Chess>();
+    let owners = world.read_storage::<Owner>();
+
+    // PvP 對戰雙方各有一筆紀錄，以主場（side 0）那筆計算
+    let fought = history
+        .rounds
+        .iter()
+        .flat_map(|round| round.matchups.iter())
+        .filter(|m| m.opponent != Opponent::Bye && !(m.side == 1 && matches!(m.opponent, Opponent::Player { .. })));
+    let (combats, timed_out_combats) = fought.fold((0, 0), |(combats, timed_out), m| {
+        let out = m.result.as_ref().map_or(false, |r| r.timed_out);
+        (combats + 1, timed_out + out as usize)
+    });
+
+    let placements: Vec<PlayerSummary> = game_state
+        .placements()
+        .into_iter()
+        .filter_map(|standing| {
+            let (player, bot) = (&players, bots.maybe()).join().find(|(p, _)| p.id == standing.player_id)?;
+            Some(PlayerSummary {
+                place: standing.place,
+                player_id: standing.player_id,
+                profile: bot.map_or("human", |bot| bot.profile.name).to_string(),
+                eliminated_round: standing.eliminated_round,
+                health: standing.health,
+                level: player.level,
+                gold: player.gold,
+                units: (&chess, &owners).join().filter(|(_, owner)| owner.player_id == player.id).count(),
+            })
+        })
+        .collect();
+
+    MatchSummary {
+        seed,
+        rounds: history.rounds.last().map_or(0, |round| round.round),
+        ticks,
+        sim_secs: ticks as f32 * config.tick_seconds(),
+        winner: placements.first().filter(|p| p.eliminated_round.is_none()).map(|p| p.player_id),
+        combats,
+        timed_out_combats,
+        placements,
+    }
+}
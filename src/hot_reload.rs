@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use crate::data::GameData;
+use crate::skills::SkillRegistry;
+
+// 資料目錄（含子目錄）中每個檔案的路徑、修改時間與大小，用來判斷檔案是否變動
+type Fingerprint = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+fn fingerprint(dir: &Path) -> Fingerprint {
+    let mut files: Fingerprint = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                files.push((entry.path(), metadata.modified().ok(), metadata.len()));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+// 監看遊戲資料目錄：檔案變動（每 poll_interval 檢查一次）或收到 SIGHUP 時重新載入，
+// 載入成功的定義送往模擬端，於下一個準備階段套用；載入失敗則保留舊資料
+pub fn spawn_watcher(data_dir: PathBuf, poll_interval: Option<Duration>, skills: SkillRegistry) -> mpsc::Receiver<GameData> {
+    let (tx, rx) = mpsc::channel(1);
+
+    tokio::spawn(async move {
+        let mut last = fingerprint(&data_dir);
+        let mut poll = poll_interval.map(tokio::time::interval);
+
+        #[cfg(unix)]
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok();
+
+        loop {
+            let forced = tokio::select! {
+                _ = async { poll.as_mut().unwrap().tick().await }, if poll.is_some() => false,
+                _ = async {
+                    #[cfg(unix)]
+                    match hangup.as_mut() {
+                        Some(hangup) => { hangup.recv().await; }
+                        None => std::future::pending::<()>().await,
+                    }
+                    #[cfg(not(unix))]
+                    std::future::pending::<()>().await
+                } => true,
+            };
+
+            let current = fingerprint(&data_dir);
+            if !forced && current == last {
+                continue;
+            }
+            last = current;
+
+            match GameData::load(&data_dir, &skills) {
+                Ok(game_data) => {
+                    println!("遊戲資料已重新載入，將於下一個準備階段套用");
+                    if tx.send(game_data).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => eprintln!("重新載入遊戲資料失敗，繼續使用舊資料: {}", e),
+            }
+        }
+    });
+
+    rx
+}
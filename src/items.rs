@@ -0,0 +1,305 @@
+use serde::{Serialize, Deserialize};
+use specs::{Component, VecStorage};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::Effect;
+use crate::data::{self, DataError};
+use crate::stats::StatModifiers;
+
+// 每個棋子最多可裝備的道具數
+pub const MAX_ITEMS_PER_UNIT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Component, // 基礎道具，可從回合掉落，兩個可合成一個完成品
+    Completed, // 由配方合成的完成品
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemDefinition {
+    pub id: String,
+    pub name: String,
+    pub kind: ItemKind,
+    #[serde(default)]
+    pub stats: StatModifiers,
+    #[serde(default)]
+    pub effect: Option<Effect>,      // 被動效果，戰鬥開始時附加
+    #[serde(default)]
+    pub components: Vec<String>,     // 完成品的配方（兩個基礎道具）
+    #[serde(default)]
+    pub drop_weight: u32,            // 基礎道具的掉落權重，0 表示不會掉落
+}
+
+impl ItemDefinition {
+    fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty() {
+            return Err("id must not be empty".to_string());
+        }
+        match self.kind {
+            ItemKind::Component if !self.components.is_empty() => {
+                Err("component items cannot have a recipe".to_string())
+            }
+            ItemKind::Completed if self.components.len() != 2 => {
+                Err("completed items need exactly two components".to_string())
+            }
+            ItemKind::Completed if self.drop_weight > 0 => {
+                Err("completed items cannot drop".to_string())
+            }
+            _ if self.effect.as_ref().map_or(false, |effect| !(effect.duration.is_finite() && effect.duration > 0.0)) => {
+                Err("effect duration must be a positive number of seconds".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ItemDefinitions {
+    items: BTreeMap<String, ItemDefinition>,
+    recipes: BTreeMap<(String, String), String>, // 兩個基礎道具（排序後）-> 完成品
+}
+
+fn recipe_key(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+impl ItemDefinitions {
+    pub fn load_dir(dir: &Path) -> Result<Self, DataError> {
+        let mut items: BTreeMap<String, ItemDefinition> = BTreeMap::new();
+        let mut sources: BTreeMap<String, PathBuf> = BTreeMap::new();
+
+        for (path, item) in data::load_json_dir::<ItemDefinition>(dir)? {
+            if let Some(first) = sources.get(&item.id) {
+                return Err(DataError::Duplicate {
+                    id: item.id.clone(),
+                    first: first.clone(),
+                    second: path,
+                });
+            }
+            item.validate().map_err(|reason| DataError::Invalid {
+                path: path.clone(),
+                id: item.id.clone(),
+                reason,
+            })?;
+            sources.insert(item.id.clone(), path);
+            items.insert(item.id.clone(), item);
+        }
+
+        // 建立配方表，並檢查配方引用的道具
+        let mut recipes: BTreeMap<(String, String), String> = BTreeMap::new();
+        for item in items.values().filter(|item| item.kind == ItemKind::Completed) {
+            let invalid = |reason: String| DataError::Invalid {
+                path: sources[&item.id].clone(),
+                id: item.id.clone(),
+                reason,
+            };
+            for component in &item.components {
+                match items.get(component) {
+                    Some(c) if c.kind == ItemKind::Component => {}
+                    Some(_) => return Err(invalid(format!("{:?} is not a component item", component))),
+                    None => return Err(invalid(format!("unknown component {:?}", component))),
+                }
+            }
+            let key = recipe_key(&item.components[0], &item.components[1]);
+            if let Some(other) = recipes.insert(key, item.id.clone()) {
+                return Err(invalid(format!("same recipe as {:?}", other)));
+            }
+        }
+
+        Ok(ItemDefinitions { items, recipes })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ItemDefinition> {
+        self.items.get(id)
+    }
+
+    pub fn definitions(&self) -> &BTreeMap<String, ItemDefinition> {
+        &self.items
+    }
+
+    // 兩個基礎道具可合成的完成品
+    pub fn combine(&self, a: &str, b: &str) -> Option<&str> {
+        self.recipes.get(&recipe_key(a, b)).map(|id| id.as_str())
+    }
+
+    // 可掉落的基礎道具與權重
+    pub fn drop_table(&self) -> Vec<(&str, u32)> {
+        self.items
+            .values()
+            .filter(|item| item.drop_weight > 0)
+            .map(|item| (item.id.as_str(), item.drop_weight))
+            .collect()
+    }
+
+    // 裝備在棋子上的道具合計加成與被動效果
+    pub fn bonuses(&self, equipped: &[String]) -> (StatModifiers, Vec<Effect>) {
+        let mut modifiers = StatModifiers::default();
+        let mut effects = Vec::new();
+        for item in equipped.iter().filter_map(|id| self.items.get(id)) {
+            modifiers.add(&item.stats);
+            effects.extend(item.effect.clone());
+        }
+        (modifiers, effects)
+    }
+}
+
+// 棋子身上的道具
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Equipment {
+    pub items: Vec<String>,
+}
+
+impl Equipment {
+    // 裝備一個道具：若身上有可合成的基礎道具則直接合成，否則佔用一個空位
+    pub fn equip(&mut self, item: &str, item_definitions: &ItemDefinitions) -> Result<(), String> {
+        let combined = self
+            .items
+            .iter()
+            .enumerate()
+            .find_map(|(index, held)| item_definitions.combine(held, item).map(|result| (index, result)));
+
+        match combined {
+            Some((index, result)) => {
+                self.items[index] = result.to_string();
+                Ok(())
+            }
+            None if self.items.len() < MAX_ITEMS_PER_UNIT => {
+                self.items.push(item.to_string());
+                Ok(())
+            }
+            None => Err(format!("unit already holds {} items", MAX_ITEMS_PER_UNIT)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPONENTS: &str = r#"
+        { "id": "sword", "name": "Sword", "kind": "component", "drop_weight": 1 },
+        { "id": "bow", "name": "Bow", "kind": "component", "drop_weight": 1 },
+        { "id": "vest", "name": "Vest", "kind": "component", "drop_weight": 1 }"#;
+
+    // 把道具定義寫入暫存目錄後載入
+    fn load(name: &str, items: &str) -> Result<ItemDefinitions, DataError> {
+        let dir = std::env::temp_dir().join(format!("ac_backend_items_{}_{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("items.json"), format!("[{}]", items)).unwrap();
+        let result = ItemDefinitions::load_dir(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        result
+    }
+
+    fn definitions() -> ItemDefinitions {
+        let items = format!(
+            r#"{},
+            {{ "id": "giant_slayer", "name": "Giant Slayer", "kind": "completed", "components": ["sword", "bow"] }},
+            {{ "id": "deathblade", "name": "Deathblade", "kind": "completed", "components": ["sword", "sword"] }}"#,
+            COMPONENTS
+        );
+        load("definitions", &items).unwrap()
+    }
+
+    fn invalid_reason(name: &str, items: &str) -> String {
+        match load(name, &format!("{}, {}", COMPONENTS, items)) {
+            Err(DataError::Invalid { reason, .. }) => reason,
+            other => panic!("expected DataError::Invalid, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn recipes_combine_in_either_order() {
+        let definitions = definitions();
+        assert_eq!(definitions.combine("sword", "bow"), Some("giant_slayer"));
+        assert_eq!(definitions.combine("bow", "sword"), Some("giant_slayer"));
+        assert_eq!(definitions.combine("bow", "vest"), None);
+
+        for (first, second) in [("sword", "bow"), ("bow", "sword")] {
+            let mut equipment = Equipment::default();
+            equipment.equip(first, &definitions).unwrap();
+            equipment.equip(second, &definitions).unwrap();
+            assert_eq!(equipment.items, vec!["giant_slayer".to_string()]);
+        }
+    }
+
+    #[test]
+    fn a_full_unit_can_still_combine_but_not_take_another_item() {
+        let definitions = definitions();
+        let mut equipment = Equipment { items: vec!["vest".to_string(), "bow".to_string(), "vest".to_string()] };
+
+        let full = equipment.equip("vest", &definitions).unwrap_err();
+        assert_eq!(full, format!("unit already holds {} items", MAX_ITEMS_PER_UNIT));
+        assert_eq!(equipment.items.len(), MAX_ITEMS_PER_UNIT);
+
+        equipment.equip("sword", &definitions).unwrap();
+        assert_eq!(equipment.items, vec!["vest".to_string(), "giant_slayer".to_string(), "vest".to_string()]);
+    }
+
+    #[test]
+    fn invalid_item_definitions_are_rejected() {
+        let cases = [
+            ("empty_id", r#"{ "id": "", "name": "Nothing", "kind": "component" }"#, "id must not be empty"),
+            (
+                "component_recipe",
+                r#"{ "id": "axe", "name": "Axe", "kind": "component", "components": ["sword", "bow"] }"#,
+                "component items cannot have a recipe",
+            ),
+            (
+                "one_component",
+                r#"{ "id": "axe", "name": "Axe", "kind": "completed", "components": ["sword"] }"#,
+                "completed items need exactly two components",
+            ),
+            (
+                "completed_drop",
+                r#"{ "id": "axe", "name": "Axe", "kind": "completed", "components": ["sword", "vest"], "drop_weight": 1 }"#,
+                "completed items cannot drop",
+            ),
+            (
+                "zero_duration",
+                r#"{ "id": "axe", "name": "Axe", "kind": "completed", "components": ["sword", "vest"],
+                     "effect": { "effect_type": "AttackBuff", "duration": 0, "magnitude": 10.0 } }"#,
+                "effect duration must be a positive number of seconds",
+            ),
+            (
+                "negative_duration",
+                r#"{ "id": "axe", "name": "Axe", "kind": "completed", "components": ["sword", "vest"],
+                     "effect": { "effect_type": "Heal", "duration": -1.5, "magnitude": 10.0 } }"#,
+                "effect duration must be a positive number of seconds",
+            ),
+            (
+                "unknown_component",
+                r#"{ "id": "axe", "name": "Axe", "kind": "completed", "components": ["sword", "rod"] }"#,
+                "unknown component \"rod\"",
+            ),
+            (
+                "completed_ingredient",
+                r#"{ "id": "axe", "name": "Axe", "kind": "completed", "components": ["sword", "vest"] },
+                   { "id": "hammer", "name": "Hammer", "kind": "completed", "components": ["axe", "bow"] }"#,
+                "\"axe\" is not a component item",
+            ),
+            (
+                "duplicate_recipe",
+                r#"{ "id": "axe", "name": "Axe", "kind": "completed", "components": ["sword", "vest"] },
+                   { "id": "hammer", "name": "Hammer", "kind": "completed", "components": ["vest", "sword"] }"#,
+                "same recipe as \"axe\"",
+            ),
+        ];
+        for (name, items, expected) in cases {
+            assert_eq!(invalid_reason(name, items), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn duplicate_item_ids_are_rejected() {
+        let items = format!(r#"{}, {{ "id": "sword", "name": "Other Sword", "kind": "component" }}"#, COMPONENTS);
+        assert!(matches!(load("duplicate_id", &items), Err(DataError::Duplicate { id, .. }) if id == "sword"));
+    }
+}
@@ -0,0 +1,172 @@
+#![allow(warnings)]
+// 自走棋後端的遊戲邏輯：ECS 元件與系統、資料載入、回合流程與連線處理。
+// 伺服器執行檔（src/main.rs）與無網路的戰鬥模擬都建立在這個函式庫上
+pub mod game_state;
+pub mod combat;
+pub mod turn;
+pub mod skills;
+pub mod players_system;
+pub mod session;
+pub mod view;
+pub mod spectator;
+pub mod server;
+pub mod config;
+pub mod data;
+pub mod units;
+pub mod hot_reload;
+pub mod stats;
+pub mod synergy;
+pub mod items;
+pub mod commands;
+pub mod rounds;
+pub mod carousel;
+pub mod rng;
+pub mod combat_log;
+pub mod replay;
+pub mod simulator;
+pub mod balance;
+pub mod battle_stats;
+pub mod shop;
+pub mod bots;
+pub mod formation;
+pub mod targeting;
+pub mod headless;
+
+use specs::{Component, VecStorage, World, WorldExt, Builder, System, ReadStorage, WriteStorage, Join};
+use specs::prelude::*;
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+use tokio::sync::mpsc;
+
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Position {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Chess {
+    id: Uuid,
+    name: String,
+    level: u32,
+    unit_id: String,     // 對應 data/units 中的棋子定義
+}
+
+// 棋子所屬的玩家
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Owner {
+    player_id: usize,
+}
+
+// 備戰席上的棋子，沒有 Position，不參與戰鬥
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Bench {
+    slot: usize,
+}
+
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct CombatStats {
+    name: String,        // 棋子的名字
+    hp: i32,
+    max_hp: i32,
+    attack: i32,
+    defense: i32,
+    magic_resist: i32,
+    attack_speed: f32,   // 每秒普攻次數
+    #[serde(default)]
+    attack_cooldown: f32, // 距離下次普攻的剩餘時間（秒）
+    #[serde(default)]
+    move_cooldown: f32,   // 距離下次移動的剩餘時間（秒）
+    attack_range: f32,    // 新增：攻擊距離
+    mana: i32,           // 當前魔力值
+    max_mana: i32,       // 最大魔力值
+    skill: Skill,        // 角色專屬技能
+    #[serde(default)]
+    targeting: targeting::TargetPolicy, // 普攻選擇目標的策略
+    #[serde(default)]
+    crit_chance: f32,    // 暴擊機率（0~1）
+    #[serde(default = "units::default_crit_damage")]
+    crit_damage: f32,    // 暴擊時的傷害倍率
+    #[serde(default)]
+    dodge_chance: f32,   // 閃避普攻的機率（0~1）
+    #[serde(default)]
+    lifesteal: f32,      // 普攻實際傷害轉為治療的比例
+    #[serde(default)]
+    spell_vamp: f32,     // 技能實際傷害轉為治療的比例
+}
+
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct StatusEffects {
+    effects: Vec<Effect>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Effect {
+    effect_type: EffectType,
+    duration: f32,       // 剩餘的持續時間（秒）
+    magnitude: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EffectType {
+    Stun,
+    Poison,        // 每秒受到 magnitude 點真實傷害
+    Heal,          // 每秒回復 magnitude 點生命
+    AttackBuff,    // 攻擊力增加 magnitude
+    DefenseBuff,   // 防禦增加 magnitude
+    CritBuff,      // 暴擊機率增加 magnitude
+    DodgeBuff,     // 閃避機率增加 magnitude
+    LifestealBuff, // 吸血比例增加 magnitude
+    SpellVampBuff, // 技能吸血比例增加 magnitude
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Skill {
+    pub id: String,               // 技能 id，對應 SkillRegistry 中註冊的執行器
+    pub damage: i32,
+    pub range: f32,
+    pub duration: Option<f32>,    // 如果是持續性效果，則有持續時間（秒）
+    pub cooldown: f32,            // 技能冷卻時間（秒），從開始施法起算
+    pub current_cooldown: f32,    // 剩餘冷卻時間（秒）
+    pub requires_mana: bool,      // 是否需要滿魔力才能施放，施放時消耗全部魔力
+    pub cast_time: f32,           // 施法前搖（秒），期間不會普攻，被暈眩會打斷
+    #[serde(default)]
+    pub targeting: targeting::TargetPolicy, // 單體技能選擇目標的策略
+}
+
+#[derive(Debug)]
+pub enum ChannelMessage {
+    WebSocketEvent(String), // Example: Message from WebSocket
+    SpecsEvent(String),     // Example: Message from Specs
+    PlayerConnected { resumed: bool }, // 玩家連線（或以 token 重連）
+    PlayerDisconnected { reason: server::DisconnectReason }, // WebSocket 斷線，進入寬限時間
+    SessionExpired,         // 寬限時間內未重連
+}
+
+// 創建兩個 channel
+use std::collections::HashMap;
+#[derive(Debug)]
+pub struct SpecsChannel {
+    pub tx_to_specs: mpsc::Sender<ChannelMessage>,
+    pub rx_from_specs: mpsc::Receiver<ChannelMessage>,
+}
+
+impl Clone for SpecsChannel {
+    fn clone(&self) -> Self {
+        SpecsChannel {
+            tx_to_specs: self.tx_to_specs.clone(),
+            rx_from_specs: panic!("Receiver cannot be cloned"),
+        }
+    }
+}
+#[derive(Debug)]
+pub struct WebsocketChannel {
+    pub tx_to_websocket: mpsc::Sender<ChannelMessage>,
+    pub rx_from_websocket: mpsc::Receiver<ChannelMessage>,
+}
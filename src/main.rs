@@ -1,163 +1,55 @@
 #![allow(warnings)]
-mod game_state;
-mod combat;
-mod turn;
-mod skills;
-mod players_system;
-
-use specs::{Component, VecStorage, World, WorldExt, Builder, System, ReadStorage, WriteStorage, Join};
+use ac_backend::{
+    battle_stats, bots, combat, commands, config, data, game_state, hot_reload, players_system, replay, server, session,
+    skills, spectator, view, ChannelMessage, SpecsChannel, WebsocketChannel,
+};
 use specs::prelude::*;
-use serde::{Serialize, Deserialize};
-use futures_util::SinkExt;
-use futures_util::StreamExt;
-use uuid::Uuid;
+use std::collections::HashMap;
 use std::time::{Instant, Duration};
 use tokio::sync::mpsc;
 use std::thread::sleep;
 
-#[derive(Component, Debug, Clone, Serialize, Deserialize)]
-#[storage(VecStorage)]
-pub struct Position {
-    x: i32,
-    y: i32,
-}
-
-#[derive(Component, Debug, Clone, Serialize, Deserialize)]
-#[storage(VecStorage)]
-pub struct Chess {
-    id: Uuid,
-    name: String,
-    level: u32,
-    chess_type: ChessType,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ChessType {
-    Warrior,
-    Mage,
-    Archer,
-    Tank,
-}
-
-#[derive(Component, Debug, Clone, Serialize, Deserialize)]
-#[storage(VecStorage)]
-pub struct CombatStats {
-    name: String,        // 棋子的名字
-    hp: i32,
-    max_hp: i32,
-    attack: i32,
-    defense: i32,
-    magic_resist: i32,
-    attack_speed: f32,
-    attack_range: f32,    // 新增：攻擊距離
-    mana: i32,           // 當前魔力值
-    max_mana: i32,       // 最大魔力值
-    skill: Skill,        // 角色專屬技能
-}
-
-#[derive(Component, Debug, Clone, Serialize, Deserialize)]
-#[storage(VecStorage)]
-pub struct StatusEffects {
-    effects: Vec<Effect>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Effect {
-    effect_type: EffectType,
-    duration: u32,
-    magnitude: f32,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum EffectType {
-    Stun,
-    Poison,
-    Heal,
-    AttackBuff,
-    DefenseBuff,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Skill {
-    pub skill_type: SkillType,
-    pub damage: i32,
-    pub range: f32,
-    pub duration: Option<u32>,    // 如果是持續性效果，則有持續時間
-    pub cooldown: u32,            // 技能冷卻時間
-    pub current_cooldown: u32,    // 當前冷卻時間
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum SkillType {
-    // 戰士：旋風斬，對周圍敵人造成傷害
-    WhirlwindSlash,
-    // 法師：火球術，對單個目標造成大量魔法傷害
-    Fireball,
-    // 弓箭手：多重射擊，對多個目標造成傷害
-    MultiShot,
-    // 坦克：護盾衝擊，增加自身防禦並眩暈敵人
-    ShieldBash,
-}
-
-// 戰鬥系統
-pub struct CombatSystem;
-
-impl<'a> System<'a> for CombatSystem {
-    type SystemData = (
-        ReadStorage<'a, Position>,
-        ReadStorage<'a, Chess>,
-        WriteStorage<'a, CombatStats>,
-        WriteStorage<'a, StatusEffects>,
-    );
-
-    fn run(&mut self, data: Self::SystemData) {
-        let (positions, chess, mut combat_stats, mut status_effects) = data;
-        // 在這裡實現戰鬥邏輯
-    }
-}
-
-#[derive(Debug)]
-pub enum ChannelMessage {
-    WebSocketEvent(String), // Example: Message from WebSocket
-    SpecsEvent(String),     // Example: Message from Specs
-}
-
-// 創建兩個 channel
-use std::collections::HashMap;
-#[derive(Debug)]
-pub struct SpecsChannel {
-    pub tx_to_specs: mpsc::Sender<ChannelMessage>,
-    pub rx_from_specs: mpsc::Receiver<ChannelMessage>,
-}
-
-impl Clone for SpecsChannel {
-    fn clone(&self) -> Self {
-        SpecsChannel {
-            tx_to_specs: self.tx_to_specs.clone(),
-            rx_from_specs: panic!("Receiver cannot be cloned"),
-        }
-    }
-}
-#[derive(Debug)]
-pub struct WebsocketChannel {
-    pub tx_to_websocket: mpsc::Sender<ChannelMessage>,
-    pub rx_from_websocket: mpsc::Receiver<ChannelMessage>,
-}
-
-
 #[tokio::main]
 async fn main() {
     env_logger::init();
-    
-    
+
+    let config = match config::ServerConfig::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("設定錯誤: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("伺服器設定: {:?}", config);
+
+    // 註冊技能，載入資料時會檢查棋子引用的技能 id
+    let skill_registry = skills::SkillRegistry::builtin();
+
+    // 載入棋子與羈絆定義
+    let data_dir = std::path::PathBuf::from(&config.data_dir);
+    let game_data = match data::GameData::load(&data_dir, &skill_registry) {
+        Ok(game_data) => game_data,
+        Err(e) => {
+            eprintln!("無法載入遊戲資料: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "已載入 {} 種棋子、{} 種羈絆、{} 種道具",
+        game_data.units.ids().len(),
+        game_data.traits.definitions().len(),
+        game_data.items.definitions().len()
+    );
+
     let mut websocket_player_channels: HashMap<String, WebsocketChannel> = HashMap::new();
     let mut specs_player_channels: HashMap<String, SpecsChannel> = HashMap::new();
 
-    // 初始化每個玩家的通道
-    for i in 0..4 {
+    // 初始化每個真人玩家的通道，電腦玩家不能透過 WebSocket 登入
+    let bot_players = config.bot_players();
+    for i in (0..config.num_players).filter(|i| !bot_players.iter().any(|(id, _)| id == i)) {
         let name_id = format!("player_{}", i);
-        let (tx_to_specs, rx_from_websocket) = mpsc::channel::<ChannelMessage>(32);
-        let (tx_to_websocket, rx_from_specs) = mpsc::channel::<ChannelMessage>(32);
+        let (tx_to_specs, rx_from_websocket) = mpsc::channel::<ChannelMessage>(config.channel_capacity);
+        let (tx_to_websocket, rx_from_specs) = mpsc::channel::<ChannelMessage>(config.channel_capacity);
         websocket_player_channels.insert(
             name_id.clone(),
             WebsocketChannel {
@@ -175,69 +67,49 @@ async fn main() {
         );
     }
 
-    // 啟動 WebSocket 伺服器
-    tokio::spawn(async move {
-        let addr = "127.0.0.1:8080";
-        let listener = tokio::net::TcpListener::bind(addr).await.expect("無法綁定 WebSocket 伺服器");
-        println!("WebSocket 伺服器正在監聽 {}", addr);
+    // 斷線後保留玩家會話的寬限時間
+    let sessions = session::SessionManager::new(
+        specs_player_channels,
+        Duration::from_secs(config.disconnect_grace_secs),
+    );
 
-        while let Ok((stream, _)) = listener.accept().await {
-            let specs_player_channels = specs_player_channels.clone(); // Use Arc to share across threads
-            tokio::spawn(async move {
-                let ws_stream = tokio_tungstenite::accept_async(stream).await.expect("無法接受 WebSocket 連線");
-                println!("新的 WebSocket 連線已建立");
-        
-                let (mut write, mut read) = ws_stream.split();
-        
-                // Wait for the first message to extract the player's name
-                if let Some(Ok(msg)) = read.next().await {
-                    if let Ok(text) = msg.to_text() {
-                        println!("收到初始訊息: {}", text);
-        
-                        // Parse JSON to extract the player's name
-                        if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(text) {
-                            if let Some(name) = parsed.get("name").and_then(|n| n.as_str()) {
-                                    let specs_channels = specs_player_channels;
-                                    if let Some(channel) = specs_channels.get(name) {
-                                    let tx_to_specs = channel.tx_to_specs.clone();
-        
-                                    // Process subsequent messages
-                                    while let Some(Ok(msg)) = read.next().await {
-                                        if let Ok(text) = msg.to_text() {
-                                            println!("收到訊息: {}", text);
-        
-                                            // Forward WebSocket message to specs via tx_to_specs
-                                            if let Err(e) = tx_to_specs.send(ChannelMessage::WebSocketEvent(text.to_string())).await {
-                                                eprintln!("Failed to send message to specs: {}", e);
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    eprintln!("No channel found for name: {}", name);
-                                }
-                            } else {
-                                eprintln!("Invalid JSON format: missing 'name' field");
-                            }
-                        } else {
-                            eprintln!("Failed to parse JSON: {}", text);
-                        }
-                    }
-                }
-            });
+    let spectators = spectator::SpectatorFeed::new(Duration::from_secs(config.spectator_delay_secs));
+    let spectator_feed = spectators.clone();
+
+    // 啟動 WebSocket 伺服器
+    let listener = match server::bind(&config.bind_address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("無法綁定 WebSocket 伺服器 {}: {}", config.bind_address, e);
+            std::process::exit(1);
         }
-    });
+    };
+    let connection_settings = server::ConnectionSettings {
+        heartbeat_interval: Duration::from_secs(config.heartbeat_interval_secs),
+        idle_timeout: Duration::from_secs(config.idle_timeout_secs),
+    };
+    tokio::spawn(server::accept_loop(listener, sessions, spectators, connection_settings));
 
     // 創建遊戲狀態
-    let mut game_state = game_state::GameState::new(websocket_player_channels);
+    let mut game_state = game_state::GameState::new(websocket_player_channels, game_data, &config);
+    let poll_interval = (config.reload_interval_secs > 0).then(|| Duration::from_secs(config.reload_interval_secs));
+    game_state.watch_game_data(hot_reload::spawn_watcher(data_dir, poll_interval, skill_registry.clone()));
+    game_state.world.insert(skill_registry);
+    game_state.world.insert(spectator_feed);
     
-    // 初始化遊戲（設置4個玩家）
-    game_state.initialize_game(4);
+    // 初始化遊戲
+    game_state.initialize_game(config.num_players);
     
     // 創建分發器
     let mut dispatcher = DispatcherBuilder::new()
         .with(combat::CombatSystem, "combat_system", &[])
-        .with(turn::TurnSystem, "turn_system", &["combat_system"])
-        .with(players_system::PlayersSystem, "players_system", &["turn_system"])
+        .with(replay::ReplaySystem, "replay_system", &["combat_system"])
+        .with(battle_stats::BattleStatsSystem, "battle_stats_system", &["combat_system"])
+        .with(players_system::PlayersSystem, "players_system", &["combat_system"])
+        .with(bots::BotSystem, "bot_system", &["combat_system"])
+        .with(commands::CommandSystem, "command_system", &["players_system", "bot_system"])
+        .with(view::ViewSystem, "view_system", &["command_system"])
+        .with(spectator::SpectatorSystem, "spectator_system", &["command_system"])
         .build();
 
     // 初始化分發器
@@ -245,26 +117,26 @@ async fn main() {
 
     println!("Auto Chess Backend initialized!");
     
-    // 遊戲主循環
-    let mut last_time = Instant::now();
-    let frame_duration = Duration::from_secs_f32(1.0 / 60.0); // 60 FPS
+    // 遊戲主循環：固定步長，每個 tick 推進相同的模擬時間。
+    // 實際時間只決定何時執行下一個 tick，落後時直接執行而不改變模擬結果
+    let tick_duration = config.tick_duration();
+    let mut next_tick = Instant::now();
 
     loop {
-        let current_time = Instant::now();
-        let delta_time = current_time.duration_since(last_time).as_secs_f32();
-        last_time = current_time;
-
         // 更新遊戲狀態
-        game_state.update(delta_time);
-        
+        game_state.update();
+
         // 運行所有系統
         dispatcher.dispatch(&mut game_state.world);
         game_state.world.maintain();
 
-        // 限制幀率
-        let elapsed = current_time.elapsed();
-        if elapsed < frame_duration {
-            sleep(frame_duration - elapsed);
+        // 等到下一個 tick；落後超過一秒時不再追趕，避免連續執行大量 tick
+        next_tick += tick_duration;
+        let now = Instant::now();
+        if next_tick > now {
+            sleep(next_tick - now);
+        } else if now - next_tick > Duration::from_secs(1) {
+            next_tick = now;
         }
     }
 }
@@ -1,27 +1,65 @@
-use specs::{System, ReadStorage, Join, Read, Write};
+use specs::{System, ReadStorage, WriteStorage, Join, Read, Write};
 use crate::turn::Player;
-use crate::WebsocketChannel;
+use crate::bots::Bot;
+use crate::view::ViewState;
+use crate::commands::{CommandQueue, InputLog, PlayerCommand, PlayerInput};
+use crate::game_state::SimTime;
+use crate::{ChannelMessage, WebsocketChannel};
 use std::collections::HashMap;
 
 pub struct PlayersSystem;
 
 impl<'a> System<'a> for PlayersSystem {
     type SystemData = (
-        ReadStorage<'a, Player>,
+        WriteStorage<'a, Player>,
+        ReadStorage<'a, Bot>,
+        Write<'a, ViewState>,
+        Write<'a, CommandQueue>,
+        Write<'a, InputLog>,
+        Read<'a, SimTime>,
         Write<'a, HashMap<String, WebsocketChannel>>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (players, mut websocket_channels) = data;
-    
-        for player in (&players).join() {
+        let (mut players, bots, mut view_state, mut command_queue, mut input_log, sim_time, mut websocket_channels) = data;
+
+        // 電腦玩家沒有 WebSocket 連線
+        for (player, _) in (&mut players, !&bots).join() {
             if let Some(channel) = websocket_channels.get_mut(&player.name_id) {
-                if let Ok(message) = channel.rx_from_websocket.try_recv() {
-                    println!("Received message for player {}: {:?}", player.name_id, message);
+                while let Ok(message) = channel.rx_from_websocket.try_recv() {
+                    match message {
+                        ChannelMessage::PlayerConnected { resumed } => {
+                            println!("Player {} connected (resumed: {})", player.name_id, resumed);
+                            player.connected = true;
+                            input_log.record(sim_time.tick, player.id, PlayerInput::Connected);
+                            // 加入或重連後送出完整狀態，讓客戶端接手控制
+                            view_state.request_snapshot(player.id);
+                        }
+                        ChannelMessage::PlayerDisconnected { reason } => {
+                            println!("Player {} disconnected ({:?}), units keep playing", player.name_id, reason);
+                            player.connected = false;
+                            input_log.record(sim_time.tick, player.id, PlayerInput::Disconnected);
+                        }
+                        ChannelMessage::SessionExpired => {
+                            println!("Player {} did not reconnect in time", player.name_id);
+                            player.connected = false;
+                            player.abandoned = true;
+                            input_log.record(sim_time.tick, player.id, PlayerInput::Abandoned);
+                        }
+                        ChannelMessage::WebSocketEvent(text) => {
+                            match serde_json::from_str::<PlayerCommand>(&text) {
+                                Ok(command) => command_queue.push(player.id, command),
+                                Err(e) => eprintln!("Invalid command from {}: {} ({})", player.name_id, text, e),
+                            }
+                        }
+                        message => {
+                            println!("Received message for player {}: {:?}", player.name_id, message);
+                        }
+                    }
                 }
             } else {
                 println!("No channel found for player {}", player.name_id);
             }
         }
     }
-}
\ No newline at end of file
+}
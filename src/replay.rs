@@ -0,0 +1,452 @@
+use serde::{Serialize, Deserialize};
+use specs::{System, Read, Write, World, WorldExt, Join};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use uuid::Uuid;
+use crate::{Chess, Position, CombatStats, StatusEffects, Effect};
+use crate::items::Equipment;
+use crate::rounds::Team;
+use crate::combat_log::{CombatEvent, CombatLog};
+use crate::game_state::SimTime;
+
+// 重播檔格式版本，格式不相容時遞增
+pub const REPLAY_VERSION: u32 = 2;
+
+// 保留最近幾個回合的重播，供客戶端觀看
+const REPLAY_HISTORY_ROUNDS: u32 = 3;
+
+// 戰鬥開始時場上的一個棋子
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayUnit {
+    pub id: Uuid,
+    pub unit_id: String,
+    pub name: String,
+    pub level: u32,
+    pub side: usize,
+    pub position: (i32, i32),
+    pub stats: CombatStats,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<Effect>,
+}
+
+// 場地中的一方：玩家或野怪陣容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaySide {
+    pub side: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_id: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub board: Option<String>,
+}
+
+// 一個 tick 內發生的事件，沒有事件的 tick 不會記錄
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub tick: u64,
+    pub events: Vec<CombatEvent>,
+}
+
+// 一場對戰（一個場地）的完整紀錄：開場陣容、種子與依 tick 排列的事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CombatReplay {
+    pub version: u32,
+    pub seed: u64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub data: String, // 戰鬥使用的遊戲資料指紋
+    pub round: u32,
+    pub arena: usize,
+    pub tick_rate: u32,
+    pub start_tick: u64,
+    pub end_tick: u64,
+    pub sides: Vec<ReplaySide>,
+    pub units: Vec<ReplayUnit>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl CombatReplay {
+    pub fn file_name(&self) -> String {
+        format!("replay_{}_round{:03}_arena{}.json", self.seed, self.round, self.arena)
+    }
+
+    // 以不縮排的 JSON 寫入檔案
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self).map_err(std::io::Error::from)
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let replay: CombatReplay = serde_json::from_reader(BufReader::new(file)).map_err(std::io::Error::from)?;
+        if replay.version != REPLAY_VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unsupported replay version {} (expected {})", replay.version, REPLAY_VERSION),
+            ));
+        }
+        Ok(replay)
+    }
+
+    pub fn involves_player(&self, player_id: usize) -> bool {
+        self.sides.iter().any(|side| side.player_id == Some(player_id))
+    }
+
+    // 重建某個 tick 結束時的場上狀態
+    pub fn state_at(&self, tick: u64) -> ReplayState {
+        let mut playback = Playback::new(self);
+        playback.seek(tick);
+        playback.state
+    }
+}
+
+// 重播中某個時間點的棋子狀態
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnitState {
+    pub id: Uuid,
+    pub unit_id: String,
+    pub side: usize,
+    pub position: (i32, i32),
+    pub hp: i32,
+    pub max_hp: i32,
+    pub alive: bool,
+    pub effects: Vec<Effect>,
+    pub casting: Option<String>, // 正在前搖的技能
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayState {
+    pub tick: u64,
+    pub units: Vec<UnitState>,
+}
+
+impl ReplayState {
+    fn initial(replay: &CombatReplay) -> Self {
+        let units = replay
+            .units
+            .iter()
+            .map(|unit| UnitState {
+                id: unit.id,
+                unit_id: unit.unit_id.clone(),
+                side: unit.side,
+                position: unit.position,
+                hp: unit.stats.hp,
+                max_hp: unit.stats.max_hp,
+                alive: unit.stats.hp > 0,
+                effects: unit.effects.clone(),
+                casting: None,
+            })
+            .collect();
+        ReplayState { tick: replay.start_tick, units }
+    }
+
+    pub fn unit(&self, id: Uuid) -> Option<&UnitState> {
+        self.units.iter().find(|unit| unit.id == id)
+    }
+
+    fn unit_mut(&mut self, id: Uuid) -> Option<&mut UnitState> {
+        self.units.iter_mut().find(|unit| unit.id == id)
+    }
+
+    fn apply(&mut self, event: &CombatEvent) {
+        match event {
+            CombatEvent::CastStarted { caster, skill, .. } => {
+                if let Some(unit) = self.unit_mut(*caster) {
+                    unit.casting = Some(skill.clone());
+                }
+            }
+            CombatEvent::CastInterrupted { caster, .. } | CombatEvent::SkillCast { caster, .. } => {
+                if let Some(unit) = self.unit_mut(*caster) {
+                    unit.casting = None;
+                }
+            }
+            CombatEvent::Damage { target, amount, .. } => {
+                if let Some(unit) = self.unit_mut(*target) {
+                    unit.hp -= amount;
+                }
+            }
+            CombatEvent::Heal { target, amount, .. } => {
+                if let Some(unit) = self.unit_mut(*target) {
+                    unit.hp = (unit.hp + amount).min(unit.max_hp);
+                }
+            }
+            CombatEvent::EffectApplied { target, effect, .. } => {
+                if let Some(unit) = self.unit_mut(*target) {
+                    unit.effects.push(effect.clone());
+                }
+            }
+            CombatEvent::EffectExpired { unit, effect_type, index } => {
+                if let Some(unit) = self.unit_mut(*unit) {
+                    if unit.effects.get(*index).map_or(false, |e| e.effect_type == *effect_type) {
+                        unit.effects.remove(*index);
+                    }
+                }
+            }
+            CombatEvent::Moved { unit, to, .. } => {
+                if let Some(unit) = self.unit_mut(*unit) {
+                    unit.position = *to;
+                }
+            }
+            CombatEvent::Summoned { caster, unit, unit_id, position, hp } => {
+                let side = self.unit(*caster).map_or(0, |caster| caster.side);
+                self.units.push(UnitState {
+                    id: *unit,
+                    unit_id: unit_id.clone(),
+                    side,
+                    position: *position,
+                    hp: *hp,
+                    max_hp: *hp,
+                    alive: true,
+                    effects: Vec::new(),
+                    casting: None,
+                });
+            }
+            CombatEvent::Died { unit, .. } => {
+                if let Some(unit) = self.unit_mut(*unit) {
+                    unit.alive = false;
+                    unit.casting = None;
+                }
+            }
+            CombatEvent::Dodged { .. } | CombatEvent::Custom { .. } => {}
+        }
+    }
+}
+
+// 依序播放重播，可以往前或往後跳到任意 tick
+pub struct Playback<'a> {
+    replay: &'a CombatReplay,
+    state: ReplayState,
+    next_frame: usize,
+}
+
+impl<'a> Playback<'a> {
+    pub fn new(replay: &'a CombatReplay) -> Self {
+        Playback {
+            replay,
+            state: ReplayState::initial(replay),
+            next_frame: 0,
+        }
+    }
+
+    pub fn state(&self) -> &ReplayState {
+        &self.state
+    }
+
+    // 跳到某個 tick 結束時的狀態；往回跳時從開場重新套用事件
+    pub fn seek(&mut self, tick: u64) -> &ReplayState {
+        if tick < self.state.tick {
+            *self = Playback::new(self.replay);
+        }
+        while let Some(frame) = self.replay.frames.get(self.next_frame) {
+            if frame.tick > tick {
+                break;
+            }
+            for event in &frame.events {
+                self.state.apply(event);
+            }
+            self.next_frame += 1;
+        }
+        self.state.tick = tick.max(self.replay.start_tick);
+        &self.state
+    }
+
+    // 前進到下一個有事件的 tick，重播結束時回傳 None
+    pub fn step(&mut self) -> Option<&ReplayFrame> {
+        let frame = self.replay.frames.get(self.next_frame)?;
+        self.seek(frame.tick);
+        Some(frame)
+    }
+}
+
+// 戰鬥開始時所有參戰棋子的開場狀態與所在場地
+pub fn starting_units(world: &World) -> Vec<(usize, ReplayUnit)> {
+    let chess = world.read_storage::<Chess>();
+    let teams = world.read_storage::<Team>();
+    let positions = world.read_storage::<Position>();
+    let combat_stats = world.read_storage::<CombatStats>();
+    let status_effects = world.read_storage::<StatusEffects>();
+    let equipment = world.read_storage::<Equipment>();
+
+    (&chess, &teams, &positions, &combat_stats, status_effects.maybe(), equipment.maybe())
+        .join()
+        .map(|(chess, team, position, stats, effects, equipment)| {
+            let unit = ReplayUnit {
+                id: chess.id,
+                unit_id: chess.unit_id.clone(),
+                name: chess.name.clone(),
+                level: chess.level,
+                side: team.side,
+                position: (position.x, position.y),
+                stats: stats.clone(),
+                items: equipment.map(|e| e.items.clone()).unwrap_or_default(),
+                effects: effects.map(|e| e.effects.clone()).unwrap_or_default(),
+            };
+            (team.arena, unit)
+        })
+        .collect()
+}
+
+// 錄製進行中的戰鬥，並保留最近幾個回合已結束的重播
+#[derive(Debug, Default)]
+pub struct ReplayRecorder {
+    recording: Vec<CombatReplay>,
+    arenas: HashMap<Uuid, usize>, // 棋子 id -> recording 中的索引
+    finished: VecDeque<CombatReplay>,
+}
+
+impl ReplayRecorder {
+    // 戰鬥開始時放入每個場地的開場陣容
+    pub fn begin(&mut self, replays: Vec<CombatReplay>) {
+        self.arenas.clear();
+        for (index, replay) in replays.iter().enumerate() {
+            for unit in &replay.units {
+                self.arenas.insert(unit.id, index);
+            }
+        }
+        self.recording = replays;
+    }
+
+    fn record(&mut self, tick: u64, events: &[CombatEvent]) {
+        for event in events {
+            let index = match self.arenas.get(&event.unit()) {
+                Some(&index) => index,
+                None => continue,
+            };
+            if let CombatEvent::Summoned { unit, .. } = event {
+                self.arenas.insert(*unit, index);
+            }
+            let frames = &mut self.recording[index].frames;
+            match frames.last_mut() {
+                Some(frame) if frame.tick == tick => frame.events.push(event.clone()),
+                _ => frames.push(ReplayFrame { tick, events: vec![event.clone()] }),
+            }
+        }
+    }
+
+    // 戰鬥結束：回傳本回合的重播，並保留在歷史中
+    pub fn finish(&mut self, end_tick: u64) -> Vec<CombatReplay> {
+        self.arenas.clear();
+        let mut replays = std::mem::take(&mut self.recording);
+        for replay in replays.iter_mut() {
+            replay.end_tick = end_tick;
+        }
+        if let Some(round) = replays.first().map(|replay| replay.round) {
+            self.finished.retain(|replay| replay.round + REPLAY_HISTORY_ROUNDS > round);
+        }
+        self.finished.extend(replays.iter().cloned());
+        replays
+    }
+
+    // 玩家某回合（未指定時為最近一回合）的重播
+    pub fn find(&self, player_id: usize, round: Option<u32>) -> Option<&CombatReplay> {
+        self.finished
+            .iter()
+            .rev()
+            .filter(|replay| round.map_or(true, |round| replay.round == round))
+            .find(|replay| replay.involves_player(player_id))
+    }
+}
+
+// 把每個 tick 的戰鬥紀錄寫入進行中的重播
+pub struct ReplaySystem;
+
+impl<'a> System<'a> for ReplaySystem {
+    type SystemData = (
+        Read<'a, CombatLog>,
+        Read<'a, SimTime>,
+        Write<'a, ReplayRecorder>,
+    );
+
+    fn run(&mut self, (combat_log, sim_time, mut recorder): Self::SystemData) {
+        if !combat_log.events.is_empty() {
+            recorder.record(sim_time.tick, &combat_log.events);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EffectType;
+    use crate::config::ServerConfig;
+    use crate::game_state::arena_position;
+    use crate::simulator::{BattleReport, BattleSimulator, BoardUnit};
+
+    // 帶有道具效果、技能與移動的一場對戰
+    fn recorded_fight() -> BattleReport {
+        let config = ServerConfig::default();
+        let data_dir = config.data_dir.clone();
+        let simulator = BattleSimulator::load(Path::new(&data_dir), config).unwrap();
+        let unit = |unit_id: &str, side: usize, position: (i32, i32), items: &[&str]| BoardUnit {
+            unit_id: unit_id.to_string(),
+            level: 1,
+            position: arena_position(side, position),
+            items: items.iter().map(|item| item.to_string()).collect(),
+        };
+        let home = [
+            unit("tank", 0, (3, 0), &["titans_resolve", "redemption"]),
+            unit("mage", 0, (2, 2), &[]),
+            unit("archer", 0, (5, 3), &["shroud"]),
+        ];
+        let away = [unit("warrior", 1, (4, 0), &["titans_resolve"]), unit("tank", 1, (1, 1), &[]), unit("archer", 1, (6, 3), &[])];
+        simulator.run(&home, &away, 5).unwrap()
+    }
+
+    #[test]
+    fn seeking_matches_stepping_from_the_start() {
+        let report = recorded_fight();
+        let replay = &report.replay;
+        let events: Vec<&CombatEvent> = replay.frames.iter().flat_map(|frame| &frame.events).collect();
+        assert!(events.iter().any(|event| matches!(event, CombatEvent::EffectExpired { .. })));
+        assert!(events.iter().any(|event| matches!(event, CombatEvent::Moved { .. })));
+
+        let mut stepping = Playback::new(replay);
+        let mut states = Vec::new();
+        while let Some(frame) = stepping.step() {
+            let tick = frame.tick;
+            states.push(stepping.state().clone());
+            assert_eq!(&replay.state_at(tick), stepping.state(), "tick {}", tick);
+        }
+
+        // 從結尾往回跳也得到相同的狀態
+        let mut seeking = Playback::new(replay);
+        for state in states.iter().rev() {
+            assert_eq!(seeking.seek(state.tick), state);
+        }
+    }
+
+    #[test]
+    fn a_recorded_fight_plays_back_to_the_simulated_result() {
+        let report = recorded_fight();
+        let path = std::env::temp_dir().join(format!("ac_backend_replay_{}.json", std::process::id()));
+        report.replay.save(&path).unwrap();
+        let loaded = CombatReplay::load(&path);
+        let _ = std::fs::remove_file(&path);
+        let loaded = loaded.unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), serde_json::to_string(&report.replay).unwrap());
+
+        let end = loaded.state_at(loaded.end_tick);
+        assert_eq!(end.units.len(), report.units.len());
+        for unit in &report.units {
+            let state = end.unit(unit.stats.id).unwrap();
+            assert_eq!((state.alive, state.hp.max(0)), (unit.alive, unit.hp), "{}", unit.stats.unit_id);
+        }
+    }
+
+    #[test]
+    fn overlapping_effects_of_the_same_type_expire_by_index() {
+        let mut replay = recorded_fight().replay;
+        let buff = |duration: f32, magnitude: f32| Effect { effect_type: EffectType::AttackBuff, duration, magnitude };
+        replay.units.truncate(1);
+        replay.units[0].effects = vec![buff(6.0, 10.0), buff(2.0, 5.0)];
+        let unit = replay.units[0].id;
+        replay.frames = vec![ReplayFrame {
+            tick: replay.start_tick + 1,
+            events: vec![CombatEvent::EffectExpired { unit, effect_type: EffectType::AttackBuff, index: 1 }],
+        }];
+
+        let state = replay.state_at(replay.start_tick + 1);
+        assert_eq!(state.unit(unit).unwrap().effects, vec![buff(6.0, 10.0)]);
+    }
+}
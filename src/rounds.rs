@@ -0,0 +1,291 @@
+use serde::{Serialize, Deserialize};
+use specs::{Component, VecStorage, NullStorage};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::data::{self, DataError};
+use crate::battle_stats::UnitBattleStats;
+use crate::game_state::{BOARD_WIDTH, BOARD_HEIGHT};
+
+// 戰鬥中的棋子所在的對戰場地與陣營，只會攻擊同一場地中不同陣營的棋子
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Team {
+    pub arena: usize,
+    pub side: usize, // 0 為主場玩家，1 為對手（玩家或野怪）
+}
+
+impl Team {
+    pub fn is_enemy(&self, other: &Team) -> bool {
+        self.arena == other.arena && self.side != other.side
+    }
+}
+
+// PvE 回合生成的野怪，結算後移除
+#[derive(Component, Debug, Clone, Default)]
+#[storage(NullStorage)]
+pub struct Creep;
+
+// 技能召喚出的棋子，結算後移除
+#[derive(Component, Debug, Clone, Default)]
+#[storage(NullStorage)]
+pub struct Summoned;
+
+// 對戰的對手
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Opponent {
+    Player { player_id: usize },
+    Creeps { board: String },
+    Bye, // 人數為奇數時輪空，不參與戰鬥
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Matchup {
+    pub arena: usize,
+    pub side: usize,
+    pub player_id: usize,
+    pub opponent: Opponent,
+    pub result: Option<MatchupResult>, // 結算後填入
+}
+
+// 一場對戰對該玩家的結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatchupResult {
+    pub won: bool,
+    pub damage: i32,
+    pub gold: i32,
+    pub items: Vec<String>,
+    pub timed_out: bool,             // 時間到時雙方都還有棋子存活
+    pub meter: Vec<UnitBattleStats>, // 這個場地雙方棋子的傷害統計
+}
+
+// 本回合的所有對戰組合，於戰鬥開始時產生，會回報給客戶端
+#[derive(Debug, Clone, Default)]
+pub struct Matchups {
+    pub round: u32,
+    pub matchups: Vec<Matchup>,
+}
+
+// 已結算回合的對戰紀錄，保留整場遊戲
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RoundHistory {
+    pub round: u32,
+    pub matchups: Vec<Matchup>,
+}
+
+#[derive(Debug, Default)]
+pub struct MatchHistory {
+    pub rounds: Vec<RoundHistory>,
+}
+
+impl MatchHistory {
+    pub fn record(&mut self, matchups: &Matchups) {
+        self.rounds.push(RoundHistory { round: matchups.round, matchups: matchups.matchups.clone() });
+    }
+
+    // 玩家每個回合的對戰與結果，由舊到新
+    pub fn for_player(&self, player_id: usize) -> Vec<RoundHistory> {
+        self.rounds
+            .iter()
+            .map(|round| RoundHistory {
+                round: round.round,
+                matchups: round.matchups.iter().filter(|m| m.player_id == player_id).cloned().collect(),
+            })
+            .filter(|round| !round.matchups.is_empty())
+            .collect()
+    }
+}
+
+// 玩家被淘汰時的回合與剩餘血量
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Elimination {
+    pub player_id: usize,
+    pub round: u32,
+    pub health: i32,
+}
+
+// 玩家的最終（或目前的）名次，eliminated_round 為 None 表示仍存活
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Standing {
+    pub place: usize,
+    pub player_id: usize,
+    pub health: i32,
+    pub eliminated_round: Option<u32>,
+}
+
+// 依淘汰先後記錄的玩家，用來決定名次
+#[derive(Debug, Default)]
+pub struct Standings {
+    pub eliminations: Vec<Elimination>,
+}
+
+impl Standings {
+    pub fn eliminate(&mut self, player_id: usize, round: u32, health: i32) {
+        if !self.eliminations.iter().any(|e| e.player_id == player_id) {
+            self.eliminations.push(Elimination { player_id, round, health });
+        }
+    }
+
+    // 存活的玩家依血量排在最前面；淘汰的玩家越晚淘汰名次越前，
+    // 同一回合淘汰時剩餘血量較高者在前，再相同時依玩家 id
+    pub fn placements(&self, alive: &[(usize, i32)]) -> Vec<Standing> {
+        let mut alive = alive.to_vec();
+        alive.sort_by_key(|&(player_id, health)| (std::cmp::Reverse(health), player_id));
+        let mut eliminated = self.eliminations.clone();
+        eliminated.sort_by_key(|e| (std::cmp::Reverse(e.round), std::cmp::Reverse(e.health), e.player_id));
+
+        alive
+            .into_iter()
+            .map(|(player_id, health)| (player_id, health, None))
+            .chain(eliminated.into_iter().map(|e| (e.player_id, e.health, Some(e.round))))
+            .enumerate()
+            .map(|(index, (player_id, health, eliminated_round))| Standing { place: index + 1, player_id, health, eliminated_round })
+            .collect()
+    }
+}
+
+// 野怪陣容中的一個棋子
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreepUnit {
+    pub unit_id: String,
+    #[serde(default = "default_level")]
+    pub level: u32,
+    pub position: (i32, i32),
+}
+
+fn default_level() -> u32 {
+    1
+}
+
+// 擊敗野怪的獎勵
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CreepReward {
+    pub gold: i32,
+    pub items: Vec<String>, // 固定給予的道具
+    pub random_items: u32,  // 從掉落表抽選的基礎道具數
+}
+
+// 資料檔中的野怪陣容，第 n 個 PvE 回合使用 from_round 不大於 n 的最後一個陣容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreepBoard {
+    pub id: String,
+    pub name: String,
+    pub from_round: u32,
+    pub units: Vec<CreepUnit>,
+    #[serde(default)]
+    pub reward: CreepReward,
+    #[serde(default)]
+    pub loss_damage: i32, // 輸給野怪時玩家扣的血量
+}
+
+impl CreepBoard {
+    fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty() {
+            return Err("id must not be empty".to_string());
+        }
+        if self.units.is_empty() {
+            return Err("at least one unit is required".to_string());
+        }
+        if self.units.iter().any(|unit| unit.level == 0) {
+            return Err("unit level must be at least 1".to_string());
+        }
+        for (index, unit) in self.units.iter().enumerate() {
+            let (x, y) = unit.position;
+            if !(0..BOARD_WIDTH).contains(&x) || !(0..BOARD_HEIGHT).contains(&y) {
+                return Err(format!("unit {:?} position ({}, {}) is off the board", unit.unit_id, x, y));
+            }
+            if self.units[..index].iter().any(|other| other.position == unit.position) {
+                return Err(format!("more than one unit at position ({}, {})", x, y));
+            }
+        }
+        if self.loss_damage < 0 || self.reward.gold < 0 {
+            return Err("loss_damage and reward gold must not be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CreepBoards {
+    boards: BTreeMap<String, CreepBoard>,
+}
+
+impl CreepBoards {
+    pub fn load_dir(dir: &Path) -> Result<Self, DataError> {
+        let mut boards: BTreeMap<String, CreepBoard> = BTreeMap::new();
+        let mut sources: BTreeMap<String, PathBuf> = BTreeMap::new();
+
+        for (path, board) in data::load_json_dir::<CreepBoard>(dir)? {
+            if let Some(first) = sources.get(&board.id) {
+                return Err(DataError::Duplicate {
+                    id: board.id.clone(),
+                    first: first.clone(),
+                    second: path,
+                });
+            }
+            board.validate().map_err(|reason| DataError::Invalid {
+                path: path.clone(),
+                id: board.id.clone(),
+                reason,
+            })?;
+            sources.insert(board.id.clone(), path);
+            boards.insert(board.id.clone(), board);
+        }
+
+        Ok(CreepBoards { boards })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CreepBoard> {
+        self.boards.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CreepBoard> {
+        self.boards.values()
+    }
+
+    pub fn definitions(&self) -> &BTreeMap<String, CreepBoard> {
+        &self.boards
+    }
+
+    // 某個 PvE 回合要面對的野怪陣容
+    pub fn for_round(&self, round: u32) -> Option<&CreepBoard> {
+        self.boards
+            .values()
+            .filter(|board| board.from_round <= round)
+            .max_by_key(|board| board.from_round)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(positions: &[(i32, i32)]) -> CreepBoard {
+        CreepBoard {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            from_round: 1,
+            units: positions.iter().map(|&position| CreepUnit { unit_id: "warrior".to_string(), level: 1, position }).collect(),
+            reward: CreepReward::default(),
+            loss_damage: 0,
+        }
+    }
+
+    #[test]
+    fn creep_positions_must_be_on_the_board() {
+        assert!(board(&[(0, 0), (BOARD_WIDTH - 1, BOARD_HEIGHT - 1)]).validate().is_ok());
+        for position in [(-1, 0), (BOARD_WIDTH, 0), (0, -1), (0, BOARD_HEIGHT)] {
+            let reason = board(&[position]).validate().unwrap_err();
+            assert!(reason.contains("off the board"), "{:?}: {}", position, reason);
+        }
+    }
+
+    #[test]
+    fn creeps_cannot_share_a_position() {
+        let reason = board(&[(1, 1), (2, 1), (1, 1)]).validate().unwrap_err();
+        assert!(reason.contains("more than one unit at position (1, 1)"), "{}", reason);
+    }
+}
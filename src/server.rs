@@ -0,0 +1,251 @@
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{Instant, Interval};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+use crate::session::SessionManager;
+use crate::spectator::{self, SpectatorFeed};
+use crate::ChannelMessage;
+
+pub type WsWrite = SplitSink<WebSocketStream<TcpStream>, Message>;
+pub type WsRead = SplitStream<WebSocketStream<TcpStream>>;
+
+// 心跳相關設定，來自 ServerConfig
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionSettings {
+    pub heartbeat_interval: Duration, // 每隔多久送出一次 ping
+    pub idle_timeout: Duration,       // 超過這段時間沒收到任何訊框就視為斷線
+}
+
+// 連線結束的原因，會一併通知模擬端
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisconnectReason {
+    Closed,          // 客戶端送出 close frame
+    TimedOut,        // 心跳逾時
+    Error(String),   // 讀寫錯誤
+}
+
+// 心跳：定期送出 ping，並記錄最後一次收到訊框的時間
+pub struct Heartbeat {
+    interval: Interval,
+    last_seen: Instant,
+    idle_timeout: Duration,
+}
+
+impl Heartbeat {
+    pub fn new(settings: ConnectionSettings) -> Self {
+        let period = settings.heartbeat_interval;
+        Heartbeat {
+            interval: tokio::time::interval_at(Instant::now() + period, period),
+            last_seen: Instant::now(),
+            idle_timeout: settings.idle_timeout,
+        }
+    }
+
+    // 收到任何訊框（包含 pong）時呼叫
+    pub fn touch(&mut self) {
+        self.last_seen = Instant::now();
+    }
+
+    pub async fn tick(&mut self) {
+        self.interval.tick().await;
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.last_seen.elapsed() >= self.idle_timeout
+    }
+}
+
+// 讀取下一個訊框：文字訊息回傳 Some，ping/pong 等控制訊框回傳 None，close 或錯誤則回傳斷線原因
+pub async fn next_text(read: &mut WsRead) -> Result<Option<String>, DisconnectReason> {
+    match read.next().await {
+        Some(Ok(Message::Text(text))) => Ok(Some(text.to_string())),
+        Some(Ok(Message::Close(_))) | None => Err(DisconnectReason::Closed),
+        Some(Ok(_)) => Ok(None),
+        Some(Err(e)) => Err(DisconnectReason::Error(e.to_string())),
+    }
+}
+
+// 等待第一則文字訊息（加入訊息）
+async fn first_text(read: &mut WsRead) -> Result<String, DisconnectReason> {
+    loop {
+        if let Some(text) = next_text(read).await? {
+            return Ok(text);
+        }
+    }
+}
+
+pub async fn bind(addr: &str) -> std::io::Result<TcpListener> {
+    let listener = TcpListener::bind(addr).await?;
+    println!("WebSocket 伺服器正在監聽 {}", addr);
+    Ok(listener)
+}
+
+pub async fn accept_loop(
+    listener: TcpListener,
+    sessions: SessionManager,
+    spectators: SpectatorFeed,
+    settings: ConnectionSettings,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                tokio::spawn(handle_connection(stream, peer, sessions.clone(), spectators.clone(), settings));
+            }
+            Err(e) => {
+                // 例如檔案描述符用盡，稍等後繼續接受連線
+                eprintln!("接受 TCP 連線失敗: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+// 處理單一 WebSocket 連線：第一則訊息為 {"name": ..., "token": ...}，
+// 不帶 token 表示首次加入，帶 token 表示斷線重連；{"role": "spectator"} 表示觀戰
+async fn handle_connection(
+    stream: TcpStream,
+    peer: std::net::SocketAddr,
+    sessions: SessionManager,
+    spectators: SpectatorFeed,
+    settings: ConnectionSettings,
+) {
+    let ws_stream = match tokio_tungstenite::accept_async(stream).await {
+        Ok(ws_stream) => ws_stream,
+        Err(e) => {
+            eprintln!("與 {} 的 WebSocket 握手失敗: {}", peer, e);
+            return;
+        }
+    };
+    println!("新的 WebSocket 連線已建立: {}", peer);
+
+    let (mut write, mut read) = ws_stream.split();
+    let mut heartbeat = Heartbeat::new(settings);
+
+    // Wait for the first message to extract the player's name
+    let first = tokio::time::timeout(
+        settings.idle_timeout,
+        first_text(&mut read),
+    )
+    .await;
+    let text = match first {
+        Ok(Ok(text)) => text,
+        Ok(Err(reason)) => {
+            println!("{} 在加入前斷線: {:?}", peer, reason);
+            return;
+        }
+        Err(_) => {
+            println!("{} 未在時限內送出加入訊息", peer);
+            let _ = write.close().await;
+            return;
+        }
+    };
+    println!("收到初始訊息: {}", text);
+
+    let parsed = match serde_json::from_str::<serde_json::Value>(&text) {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            eprintln!("Failed to parse JSON: {}", text);
+            return;
+        }
+    };
+    if parsed.get("role").and_then(|r| r.as_str()) == Some("spectator") {
+        spectator::run_spectator(write, read, heartbeat, spectators).await;
+        return;
+    }
+    let name = match parsed.get("name").and_then(|n| n.as_str()) {
+        Some(name) => name.to_string(),
+        None => {
+            eprintln!("Invalid JSON format: missing 'name' field");
+            return;
+        }
+    };
+    let token = parsed
+        .get("token")
+        .and_then(|t| t.as_str())
+        .and_then(|t| Uuid::parse_str(t).ok());
+
+    let handle = match sessions.join(&name, token) {
+        Ok(handle) => handle,
+        Err(e) => {
+            eprintln!("玩家 {} 加入失敗: {}", name, e);
+            let reply = serde_json::json!({ "type": "error", "reason": e.to_string() });
+            let _ = write.send(Message::text(reply.to_string())).await;
+            let _ = write.close().await;
+            return;
+        }
+    };
+
+    let reply = serde_json::json!({
+        "type": "session",
+        "name": handle.name,
+        "token": handle.token.to_string(),
+        "resumed": handle.resumed,
+    });
+    if write.send(Message::text(reply.to_string())).await.is_err() {
+        sessions.disconnect(&handle.name, handle.generation);
+        return;
+    }
+
+    let mut rx_from_specs = handle.rx_from_specs.lock().await;
+    // 斷線期間累積的訊息已過時，重連後由模擬端重新送出完整狀態
+    while rx_from_specs.try_recv().is_ok() {}
+
+    let tx_to_specs = handle.tx_to_specs.clone();
+    if let Err(e) = tx_to_specs.send(ChannelMessage::PlayerConnected { resumed: handle.resumed }).await {
+        eprintln!("Failed to send message to specs: {}", e);
+    }
+
+    // Process subsequent messages
+    let reason = loop {
+        tokio::select! {
+            incoming = next_text(&mut read) => {
+                let text = match incoming {
+                    Ok(Some(text)) => text,
+                    Ok(None) => {
+                        heartbeat.touch();
+                        continue;
+                    }
+                    Err(reason) => break reason,
+                };
+                heartbeat.touch();
+                println!("收到訊息: {}", text);
+
+                // Forward WebSocket message to specs via tx_to_specs
+                if let Err(e) = tx_to_specs.send(ChannelMessage::WebSocketEvent(text)).await {
+                    eprintln!("Failed to send message to specs: {}", e);
+                }
+            }
+            outgoing = rx_from_specs.recv() => {
+                match outgoing {
+                    Some(ChannelMessage::SpecsEvent(text)) => {
+                        if let Err(e) = write.send(Message::text(text)).await {
+                            break DisconnectReason::Error(e.to_string());
+                        }
+                    }
+                    Some(_) => {}
+                    None => break DisconnectReason::Closed,
+                }
+            }
+            _ = heartbeat.tick() => {
+                if heartbeat.timed_out() {
+                    break DisconnectReason::TimedOut;
+                }
+                if let Err(e) = write.send(Message::Ping(Default::default())).await {
+                    break DisconnectReason::Error(e.to_string());
+                }
+            }
+        }
+    };
+
+    println!("玩家 {} 的 WebSocket 連線已中斷: {:?}", handle.name, reason);
+    let _ = write.close().await;
+    drop(rx_from_specs);
+    if let Err(e) = tx_to_specs.send(ChannelMessage::PlayerDisconnected { reason }).await {
+        eprintln!("Failed to send message to specs: {}", e);
+    }
+    sessions.disconnect(&handle.name, handle.generation);
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use crate::{ChannelMessage, SpecsChannel};
+
+// 玩家連線會話：WebSocket 斷線後 rx_from_specs 仍保留在這裡，重連時交給新的連線
+pub struct PlayerSession {
+    pub token: Option<Uuid>,      // 首次加入時發放的會話憑證
+    pub connected: bool,
+    pub expired: bool,            // 超過斷線寬限時間後不可再重連
+    generation: u64,              // 每次連線遞增，用來判斷寬限計時是否過期
+    tx_to_specs: mpsc::Sender<ChannelMessage>,
+    rx_from_specs: Arc<tokio::sync::Mutex<mpsc::Receiver<ChannelMessage>>>,
+}
+
+// 成功加入（或重連）後交給連線任務的資料
+pub struct SessionHandle {
+    pub name: String,
+    pub token: Uuid,
+    pub resumed: bool,
+    pub generation: u64,
+    pub tx_to_specs: mpsc::Sender<ChannelMessage>,
+    pub rx_from_specs: Arc<tokio::sync::Mutex<mpsc::Receiver<ChannelMessage>>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinError {
+    UnknownPlayer,
+    InvalidToken,
+    AlreadyConnected,
+    SessionExpired,
+}
+
+impl std::fmt::Display for JoinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            JoinError::UnknownPlayer => write!(f, "unknown player"),
+            JoinError::InvalidToken => write!(f, "invalid session token"),
+            JoinError::AlreadyConnected => write!(f, "player already connected"),
+            JoinError::SessionExpired => write!(f, "session expired"),
+        }
+    }
+}
+
+// 所有玩家會話的共用表，可在連線任務之間複製
+#[derive(Clone)]
+pub struct SessionManager {
+    sessions: Arc<Mutex<HashMap<String, PlayerSession>>>,
+    grace_period: Duration,
+}
+
+impl SessionManager {
+    pub fn new(specs_player_channels: HashMap<String, SpecsChannel>, grace_period: Duration) -> Self {
+        let sessions = specs_player_channels
+            .into_iter()
+            .map(|(name_id, channel)| {
+                (
+                    name_id,
+                    PlayerSession {
+                        token: None,
+                        connected: false,
+                        expired: false,
+                        generation: 0,
+                        tx_to_specs: channel.tx_to_specs,
+                        rx_from_specs: Arc::new(tokio::sync::Mutex::new(channel.rx_from_specs)),
+                    },
+                )
+            })
+            .collect();
+
+        SessionManager {
+            sessions: Arc::new(Mutex::new(sessions)),
+            grace_period,
+        }
+    }
+
+    // 首次加入時不帶 token，伺服器發放新的 token；之後重連必須帶上相同的 token
+    pub fn join(&self, name: &str, token: Option<Uuid>) -> Result<SessionHandle, JoinError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(name).ok_or(JoinError::UnknownPlayer)?;
+
+        if session.expired {
+            return Err(JoinError::SessionExpired);
+        }
+
+        let resumed = match (session.token, token) {
+            (None, None) => {
+                session.token = Some(Uuid::new_v4());
+                false
+            }
+            (Some(issued), Some(given)) if issued == given => true,
+            _ => return Err(JoinError::InvalidToken),
+        };
+
+        if session.connected {
+            return Err(JoinError::AlreadyConnected);
+        }
+
+        session.connected = true;
+        session.generation += 1;
+
+        Ok(SessionHandle {
+            name: name.to_string(),
+            token: session.token.unwrap(),
+            resumed,
+            generation: session.generation,
+            tx_to_specs: session.tx_to_specs.clone(),
+            rx_from_specs: session.rx_from_specs.clone(),
+        })
+    }
+
+    // 連線結束時呼叫；寬限時間內沒有重連就讓會話失效並通知模擬端
+    pub fn disconnect(&self, name: &str, generation: u64) {
+        let tx_to_specs = {
+            let mut sessions = self.sessions.lock().unwrap();
+            match sessions.get_mut(name) {
+                Some(session) if session.generation == generation => {
+                    session.connected = false;
+                    session.tx_to_specs.clone()
+                }
+                _ => return,
+            }
+        };
+
+        let sessions = self.sessions.clone();
+        let grace_period = self.grace_period;
+        let name = name.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(grace_period).await;
+            let expired = {
+                let mut sessions = sessions.lock().unwrap();
+                match sessions.get_mut(&name) {
+                    Some(session) if session.generation == generation && !session.connected => {
+                        session.expired = true;
+                        true
+                    }
+                    _ => false,
+                }
+            };
+            if expired {
+                println!("玩家 {} 斷線超過寬限時間，會話失效", name);
+                if let Err(e) = tx_to_specs.send(ChannelMessage::SessionExpired).await {
+                    eprintln!("Failed to send message to specs: {}", e);
+                }
+            }
+        });
+    }
+}
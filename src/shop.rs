@@ -0,0 +1,92 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::config::ServerConfig;
+use crate::rng::SimRng;
+use crate::turn::Player;
+use crate::units::{UnitDefinitions, MAX_COST};
+
+// 商店每次提供的棋子數
+pub const SHOP_SIZE: usize = 5;
+
+// 玩家等級上限，場上可放的棋子數等於等級
+pub const MAX_LEVEL: u32 = 9;
+
+// 每次購買經驗獲得的經驗值
+pub const EXPERIENCE_PER_PURCHASE: u32 = 4;
+
+// 每回合準備階段自動獲得的經驗值
+pub const EXPERIENCE_PER_ROUND: u32 = 2;
+
+// 從 index 等級升到下一級所需的經驗值
+const LEVEL_EXPERIENCE: [u32; MAX_LEVEL as usize] = [2, 2, 6, 10, 20, 36, 56, 80, 0];
+
+// 各等級刷到 1~5 費棋子的機率（百分比）
+const SHOP_ODDS: [[u32; MAX_COST as usize]; MAX_LEVEL as usize] = [
+    [100, 0, 0, 0, 0],
+    [100, 0, 0, 0, 0],
+    [75, 25, 0, 0, 0],
+    [55, 30, 15, 0, 0],
+    [45, 33, 20, 2, 0],
+    [30, 40, 25, 5, 0],
+    [19, 30, 35, 15, 1],
+    [15, 20, 35, 25, 5],
+    [10, 15, 30, 30, 15],
+];
+
+// 升到下一級還需要的經驗值，已達上限時回傳 None
+pub fn experience_to_next(player: &Player) -> Option<u32> {
+    if player.level >= MAX_LEVEL {
+        return None;
+    }
+    Some(LEVEL_EXPERIENCE[player.level as usize - 1].saturating_sub(player.experience))
+}
+
+// 增加經驗值，足夠時自動升級
+pub fn add_experience(player: &mut Player, amount: u32) {
+    player.experience += amount;
+    while player.level < MAX_LEVEL {
+        let needed = LEVEL_EXPERIENCE[player.level as usize - 1];
+        if player.experience < needed {
+            break;
+        }
+        player.experience -= needed;
+        player.level += 1;
+    }
+    if player.level >= MAX_LEVEL {
+        player.experience = 0;
+    }
+}
+
+// 每回合的收入：基本收入加上每 10 金幣 1 金幣的利息
+pub fn round_income(player: &Player, config: &ServerConfig) -> i32 {
+    config.round_income + (player.gold.max(0) / 10).min(config.max_interest)
+}
+
+// 依玩家等級的機率刷新商店；某個費用沒有棋子時改用其他有棋子的費用
+pub fn roll_shop(level: u32, units: &UnitDefinitions, rng: &mut SimRng) -> Vec<String> {
+    let mut tiers: Vec<Vec<&str>> = vec![Vec::new(); MAX_COST as usize];
+    for definition in units.iter() {
+        tiers[definition.cost as usize - 1].push(definition.id.as_str());
+    }
+    let odds = &SHOP_ODDS[(level.clamp(1, MAX_LEVEL) - 1) as usize];
+    let weights: Vec<u32> = odds.iter().zip(&tiers).map(|(odds, tier)| if tier.is_empty() { 0 } else { *odds }).collect();
+    let total: u32 = weights.iter().sum();
+
+    let mut shop = Vec::with_capacity(SHOP_SIZE);
+    for _ in 0..SHOP_SIZE {
+        let tier = if total > 0 {
+            let mut roll = rng.gen_range(0..total);
+            weights.iter().position(|&w| if roll < w { true } else { roll -= w; false }).unwrap()
+        } else {
+            // 這個等級可刷的費用都沒有棋子，從最便宜的有棋子的費用中挑選
+            match tiers.iter().position(|tier| !tier.is_empty()) {
+                Some(tier) => tier,
+                None => break,
+            }
+        };
+        if let Some(unit_id) = tiers[tier].choose(rng) {
+            shop.push(unit_id.to_string());
+        }
+    }
+    shop
+}
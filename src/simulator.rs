@@ -0,0 +1,260 @@
+use serde::{Serialize, Deserialize};
+use specs::{World, WorldExt, Builder, Join, DispatcherBuilder};
+use std::path::Path;
+use uuid::Uuid;
+use crate::{Position, Chess, CombatStats, StatusEffects, Owner};
+use crate::combat::{Casting, CombatSystem};
+use crate::battle_stats::{BattleStats, BattleStatsSystem, UnitBattleStats};
+use crate::combat_log::CombatLog;
+use crate::config::ServerConfig;
+use crate::data::{DataError, DataFingerprint, GameData};
+use crate::game_state::{self, SimTime};
+use crate::items::Equipment;
+use crate::replay::{self, CombatReplay, ReplayRecorder, ReplaySide, ReplaySystem, REPLAY_VERSION};
+use crate::rng::SimRng;
+use crate::rounds::{Team, Summoned};
+use crate::skills::SkillRegistry;
+use crate::targeting::CurrentTarget;
+
+// 模擬用棋盤上的一個棋子
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardUnit {
+    pub unit_id: String,
+    #[serde(default = "default_level")]
+    pub level: u32,
+    pub position: (i32, i32),
+    #[serde(default)]
+    pub items: Vec<String>, // 依序裝備，可合成的基礎道具會自動合成
+}
+
+fn default_level() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BattleOutcome {
+    HomeWin,
+    AwayWin,
+    Draw, // 時間到雙方都有存活，或同時全滅
+}
+
+// 一個棋子在這場戰鬥中的結果與傷害統計
+#[derive(Debug, Clone, Serialize)]
+pub struct UnitReport {
+    #[serde(flatten)]
+    pub stats: UnitBattleStats,
+    pub level: u32,
+    pub alive: bool,
+    pub hp: i32,
+    pub max_hp: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BattleReport {
+    pub outcome: BattleOutcome,
+    pub ticks: u64,
+    pub duration_secs: f32,
+    pub units: Vec<UnitReport>,
+    #[serde(skip)]
+    pub replay: CombatReplay,
+}
+
+#[derive(Debug)]
+pub enum SimulationError {
+    UnknownUnit { side: usize, unit_id: String },
+    UnknownItem { side: usize, item: String },
+    InvalidUnit { side: usize, unit_id: String, reason: String },
+}
+
+impl std::fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SimulationError::UnknownUnit { side, unit_id } => write!(f, "side {}: unknown unit {:?}", side, unit_id),
+            SimulationError::UnknownItem { side, item } => write!(f, "side {}: unknown item {:?}", side, item),
+            SimulationError::InvalidUnit { side, unit_id, reason } => write!(f, "side {}: {:?} {}", side, unit_id, reason),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+// 不經過網路與回合流程，直接在獨立的 world 中執行一場兩方對戰
+#[derive(Clone)]
+pub struct BattleSimulator {
+    data: GameData,
+    skills: SkillRegistry,
+    config: ServerConfig, // 使用 tick_rate、combat_time 與魔力規則
+}
+
+impl BattleSimulator {
+    pub fn new(data: GameData, skills: SkillRegistry, config: ServerConfig) -> Self {
+        BattleSimulator { data, skills, config }
+    }
+
+    // 以內建技能載入資料目錄
+    pub fn load(data_dir: &Path, config: ServerConfig) -> Result<Self, DataError> {
+        let skills = SkillRegistry::builtin();
+        let data = GameData::load(data_dir, &skills)?;
+        Ok(BattleSimulator::new(data, skills, config))
+    }
+
+    pub fn data(&self) -> &GameData {
+        &self.data
+    }
+
+    // home 為陣營 0、away 為陣營 1；相同的棋盤與種子會得到相同的結果
+    pub fn run(&self, home: &[BoardUnit], away: &[BoardUnit], seed: u64) -> Result<BattleReport, SimulationError> {
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<Chess>();
+        world.register::<CombatStats>();
+        world.register::<StatusEffects>();
+        world.register::<Owner>();
+        world.register::<Equipment>();
+        world.register::<Team>();
+        world.register::<Summoned>();
+        world.register::<Casting>();
+        world.register::<CurrentTarget>();
+        self.data.clone().insert_into(&mut world);
+        world.insert(self.skills.clone());
+        world.insert(self.config.clone());
+        world.insert(SimRng::seeded(seed));
+        world.insert(SimTime::default());
+        world.insert(CombatLog::default());
+        world.insert(ReplayRecorder::default());
+        world.insert(BattleStats::default());
+
+        for (side, board) in [home, away].into_iter().enumerate() {
+            for unit in board {
+                self.spawn(&mut world, side, unit)?;
+            }
+        }
+        game_state::prepare_combat_stats(&world);
+
+        let starting_units = replay::starting_units(&world);
+        world.write_resource::<BattleStats>().begin(&starting_units);
+        let replay = CombatReplay {
+            version: REPLAY_VERSION,
+            seed,
+            data: world.read_resource::<DataFingerprint>().0.clone(),
+            round: 0,
+            arena: 0,
+            tick_rate: self.config.tick_rate,
+            start_tick: 0,
+            end_tick: 0,
+            sides: (0..2).map(|side| ReplaySide { side, player_id: None, board: None }).collect(),
+            units: starting_units.into_iter().map(|(_, unit)| unit).collect(),
+            frames: Vec::new(),
+        };
+        world.write_resource::<ReplayRecorder>().begin(vec![replay]);
+
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(CombatSystem, "combat_system", &[])
+            .with(ReplaySystem, "replay_system", &["combat_system"])
+            .with(BattleStatsSystem, "battle_stats_system", &["combat_system"])
+            .build();
+        dispatcher.setup(&mut world);
+
+        let max_ticks = (self.config.combat_time * self.config.tick_rate as f32).ceil() as u64;
+        let mut ticks = 0;
+        let mut alive = [true, true];
+        while ticks < max_ticks && alive == [true, true] {
+            ticks += 1;
+            {
+                let mut sim_time = world.write_resource::<SimTime>();
+                sim_time.tick = ticks;
+                sim_time.delta = self.config.tick_seconds();
+            }
+            dispatcher.dispatch(&world);
+            world.maintain();
+            alive = sides_alive(&world);
+        }
+
+        let outcome = match alive {
+            [true, false] => BattleOutcome::HomeWin,
+            [false, true] => BattleOutcome::AwayWin,
+            _ => BattleOutcome::Draw,
+        };
+        let units = {
+            let chess = world.read_storage::<Chess>();
+            let teams = world.read_storage::<Team>();
+            let combat_stats = world.read_storage::<CombatStats>();
+            let battle_stats = world.read_resource::<BattleStats>();
+            (&chess, &teams, &combat_stats)
+                .join()
+                .map(|(chess, team, stats)| UnitReport {
+                    stats: battle_stats
+                        .unit(chess.id)
+                        .cloned()
+                        .unwrap_or_else(|| UnitBattleStats::new(chess.id, &chess.unit_id, team.side)),
+                    level: chess.level,
+                    alive: stats.hp > 0,
+                    hp: stats.hp.max(0),
+                    max_hp: stats.max_hp,
+                })
+                .collect()
+        };
+        let mut replays = world.write_resource::<ReplayRecorder>().finish(ticks);
+
+        Ok(BattleReport {
+            outcome,
+            ticks,
+            duration_secs: ticks as f32 * self.config.tick_seconds(),
+            units,
+            replay: replays.remove(0),
+        })
+    }
+
+    fn spawn(&self, world: &mut World, side: usize, unit: &BoardUnit) -> Result<(), SimulationError> {
+        let definition = self.data.units.get(&unit.unit_id).ok_or_else(|| SimulationError::UnknownUnit {
+            side,
+            unit_id: unit.unit_id.clone(),
+        })?;
+        if unit.level == 0 {
+            return Err(SimulationError::InvalidUnit { side, unit_id: unit.unit_id.clone(), reason: "level must be at least 1".to_string() });
+        }
+        let mut equipment = Equipment::default();
+        for item in &unit.items {
+            if self.data.items.get(item).is_none() {
+                return Err(SimulationError::UnknownItem { side, item: item.clone() });
+            }
+            equipment
+                .equip(item, &self.data.items)
+                .map_err(|reason| SimulationError::InvalidUnit { side, unit_id: unit.unit_id.clone(), reason })?;
+        }
+
+        let id = world.write_resource::<SimRng>().uuid();
+        let (x, y) = unit.position;
+        world
+            .create_entity()
+            .with(Chess {
+                id,
+                name: definition.name.clone(),
+                level: unit.level,
+                unit_id: unit.unit_id.clone(),
+            })
+            .with(Owner { player_id: side })
+            .with(Position { x, y })
+            .with(definition.combat_stats(unit.level))
+            .with(StatusEffects { effects: Vec::new() })
+            .with(equipment)
+            .with(Team { arena: 0, side })
+            .build();
+        Ok(())
+    }
+}
+
+// 兩個陣營是否還有存活的棋子
+fn sides_alive(world: &World) -> [bool; 2] {
+    let teams = world.read_storage::<Team>();
+    let combat_stats = world.read_storage::<CombatStats>();
+    let mut alive = [false, false];
+    for (team, stats) in (&teams, &combat_stats).join() {
+        if stats.hp > 0 && team.side < 2 {
+            alive[team.side] = true;
+        }
+    }
+    alive
+}
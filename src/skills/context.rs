@@ -0,0 +1,304 @@
+use specs::{Entity, Entities, Join, LazyUpdate, ReadStorage, WriteStorage, Builder};
+use uuid::Uuid;
+use crate::{Position, Chess, CombatStats, StatusEffects, Effect};
+use crate::combat::{self, Casting, ManaRules};
+use crate::combat_log::{CombatEvent, DamageKind, VampKind};
+use crate::rounds::{Team, Summoned};
+use crate::rng::SimRng;
+use crate::game_state::{BOARD_WIDTH, BOARD_HEIGHT};
+use crate::stats::{self, HitStats};
+use rand::Rng;
+use crate::units::UnitDefinitions;
+use crate::targeting::{self, Candidate, TargetPolicy};
+
+// 技能執行時可用的戰鬥狀態與操作。傷害、治療、效果、位移與召喚都會寫入戰鬥紀錄
+pub struct SkillContext<'a, 'b> {
+    pub caster: Entity,
+    pub skill_id: &'a str,
+    pub damage: i32, // 技能傷害（已含星級與加成）
+    pub range: f32,  // 技能範圍
+    pub duration: Option<f32>, // 持續性效果的持續時間（秒）
+    pub entities: &'a Entities<'b>,
+    pub chess: &'a ReadStorage<'b, Chess>,
+    pub teams: &'a ReadStorage<'b, Team>,
+    pub positions: &'a mut WriteStorage<'b, Position>,
+    pub combat_stats: &'a mut WriteStorage<'b, CombatStats>,
+    pub status_effects: &'a mut WriteStorage<'b, StatusEffects>,
+    pub castings: &'a WriteStorage<'b, Casting>,
+    pub targeting: TargetPolicy,           // 技能的目標策略，供 targets 使用
+    pub current_target: Option<Entity>,    // 施法者目前的普攻目標
+    pub mana_rules: ManaRules,
+    pub unit_definitions: &'a UnitDefinitions,
+    pub lazy: &'a LazyUpdate,
+    pub rng: &'a mut SimRng,
+    pub events: &'a mut Vec<CombatEvent>,
+}
+
+impl<'a, 'b> SkillContext<'a, 'b> {
+    // 棋子在戰鬥紀錄中的 id
+    pub fn unit_id(&self, entity: Entity) -> Uuid {
+        self.chess.get(entity).map(|c| c.id).unwrap_or_default()
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<Position> {
+        self.positions.get(entity).cloned()
+    }
+
+    pub fn distance(&self, a: Entity, b: Entity) -> Option<f32> {
+        match (self.positions.get(a), self.positions.get(b)) {
+            (Some(a), Some(b)) => Some(distance(a, b)),
+            _ => None,
+        }
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.combat_stats.get(entity).map_or(false, |stats| stats.hp > 0)
+    }
+
+    pub fn is_enemy(&self, a: Entity, b: Entity) -> bool {
+        match (self.teams.get(a), self.teams.get(b)) {
+            (Some(a), Some(b)) => a.is_enemy(b),
+            _ => false,
+        }
+    }
+
+    pub fn is_ally(&self, a: Entity, b: Entity) -> bool {
+        match (self.teams.get(a), self.teams.get(b)) {
+            (Some(a), Some(b)) => a.arena == b.arena && a.side == b.side,
+            _ => false,
+        }
+    }
+
+    // 施法者 range 範圍內存活的敵方棋子，依距離由近到遠排序，距離相同時依 entity id 排序
+    pub fn enemies_within(&self, range: f32) -> Vec<Entity> {
+        self.units_within(range, |ctx, entity| ctx.is_enemy(self.caster, entity))
+    }
+
+    // 施法者 range 範圍內存活的友方棋子（含施法者本身）
+    pub fn allies_within(&self, range: f32) -> Vec<Entity> {
+        self.units_within(range, |ctx, entity| ctx.is_ally(self.caster, entity))
+    }
+
+    // 施法者 range 範圍內存活的敵方棋子，依技能的目標策略排列，第一個為單體技能的目標
+    pub fn targets(&mut self, range: f32) -> Vec<Entity> {
+        let candidates: Vec<Candidate> = self
+            .enemies_within(range)
+            .into_iter()
+            .filter_map(|entity| {
+                let stats = self.combat_stats.get(entity)?;
+                let distance = self.distance(self.caster, entity)?;
+                Some(Candidate { entity, distance, hp: stats.hp, attack: stats.attack })
+            })
+            .collect();
+        targeting::rank(self.targeting, candidates, self.current_target, self.rng)
+    }
+
+    pub fn nearest_enemy(&self) -> Option<Entity> {
+        self.enemies_within(f32::MAX).into_iter().next()
+    }
+
+    fn units_within(&self, range: f32, filter: impl Fn(&Self, Entity) -> bool) -> Vec<Entity> {
+        let origin = match self.positions.get(self.caster) {
+            Some(origin) => origin.clone(),
+            None => return Vec::new(),
+        };
+        let mut found: Vec<(f32, Entity)> = (self.entities, &*self.positions)
+            .join()
+            .filter(|(entity, _)| self.is_alive(*entity) && filter(self, *entity))
+            .map(|(entity, position)| (distance(&origin, position), entity))
+            .filter(|(d, _)| *d <= range)
+            .collect();
+        found.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.id().cmp(&b.1.id())));
+        found.into_iter().map(|(_, entity)| entity).collect()
+    }
+
+    // 對目標造成傷害，先判定暴擊，再依類型以防禦或魔抗減免，回傳實際扣除的生命值。
+    // 目標依減免前傷害獲得魔力，施法者依技能吸血比例回復生命。技能不會被閃避
+    pub fn deal_damage(&mut self, target: Entity, amount: i32, kind: DamageKind) -> i32 {
+        let caster = match self.combat_stats.get(self.caster) {
+            Some(stats) => HitStats::of(stats, self.status_effects.get(self.caster)),
+            None => return 0,
+        };
+        let target_hit = match self.combat_stats.get(target) {
+            Some(stats) if stats.hp > 0 => HitStats::of(stats, self.status_effects.get(target)),
+            _ => return 0,
+        };
+        let (amount, crit) = match roll_hit(amount, &caster, &target_hit, false, self.rng) {
+            HitRoll::Hit { amount, crit } => (amount, crit),
+            HitRoll::Dodged => return 0,
+        };
+        let casting = self.castings.contains(target);
+        let dealt = mitigate(amount, kind, &target_hit);
+        let stats = self.combat_stats.get_mut(target).unwrap();
+        stats.hp -= dealt;
+        combat::gain_mana(stats, casting, self.mana_rules.from_damage_taken(amount));
+        let died = stats.hp <= 0;
+
+        let (source, target_id) = (self.unit_id(self.caster), self.unit_id(target));
+        self.events.push(CombatEvent::Damage {
+            source,
+            target: target_id,
+            amount: dealt,
+            mitigated: amount.max(0) - dealt,
+            kind,
+            skill: Some(self.skill_id.to_string()),
+            crit,
+        });
+        if died {
+            self.events.push(CombatEvent::Died { unit: target_id, killer: Some(source) });
+        }
+        let healed = self.combat_stats.get_mut(self.caster).map_or(0, |stats| vamp(stats, dealt, caster.spell_vamp));
+        if healed > 0 {
+            self.events.push(CombatEvent::Heal { source, target: source, amount: healed, vamp: Some(VampKind::SpellVamp) });
+        }
+        dealt
+    }
+
+    // 治療目標，不超過最大生命值，回傳實際回復量
+    pub fn heal(&mut self, target: Entity, amount: i32) -> i32 {
+        let stats = match self.combat_stats.get_mut(target) {
+            Some(stats) if stats.hp > 0 => stats,
+            _ => return 0,
+        };
+        let healed = amount.max(0).min(stats.max_hp - stats.hp);
+        stats.hp += healed;
+        let (source, target) = (self.unit_id(self.caster), self.unit_id(target));
+        self.events.push(CombatEvent::Heal { source, target, amount: healed, vamp: None });
+        healed
+    }
+
+    // 對存活的棋子套用效果，已陣亡的棋子不會再獲得效果
+    pub fn apply_effect(&mut self, target: Entity, effect: Effect) {
+        if !self.is_alive(target) {
+            return;
+        }
+        if let Some(effects) = self.status_effects.get_mut(target) {
+            effects.effects.push(effect.clone());
+            let (source, target) = (self.unit_id(self.caster), self.unit_id(target));
+            self.events.push(CombatEvent::EffectApplied { source, target, effect });
+        }
+    }
+
+    // 把棋子移到指定位置（衝鋒、擊退等）
+    pub fn move_unit(&mut self, unit: Entity, to: Position) {
+        if let Some(position) = self.positions.get_mut(unit) {
+            let from = (position.x, position.y);
+            *position = to.clone();
+            let unit = self.unit_id(unit);
+            self.events.push(CombatEvent::Moved { unit, from, to: (to.x, to.y) });
+        }
+    }
+
+    // 在施法者的陣營召喚一個棋子，於本 tick 結束後生成，結算時移除。
+    // 指定位置在場地外或已有棋子時改放在最近的空格，場地已滿時不召喚並回傳 None
+    pub fn summon(&mut self, unit_id: &str, level: u32, position: Position) -> Option<Uuid> {
+        let definition = self.unit_definitions.get(unit_id)?;
+        let team = *self.teams.get(self.caster)?;
+        let position = self.free_cell_near(team.arena, &position)?;
+        let id = self.rng.uuid();
+        let stats = stats::effective_stats(definition, level, &Default::default());
+        let (x, y, hp) = (position.x, position.y, stats.hp);
+        self.lazy
+            .create_entity(self.entities)
+            .with(Chess {
+                id,
+                name: definition.name.clone(),
+                level,
+                unit_id: unit_id.to_string(),
+            })
+            .with(position)
+            .with(stats)
+            .with(StatusEffects { effects: Vec::new() })
+            .with(team)
+            .with(Summoned)
+            .build();
+        let caster = self.unit_id(self.caster);
+        self.events.push(CombatEvent::Summoned { caster, unit: id, unit_id: unit_id.to_string(), position: (x, y), hp });
+        Some(id)
+    }
+
+    // 場地中離 wanted 最近、沒有存活棋子的格子，距離相同時取 y、x 較小者。
+    // 本 tick 已召喚但尚未生成的棋子也佔用格子
+    fn free_cell_near(&self, arena: usize, wanted: &Position) -> Option<Position> {
+        let mut occupied: Vec<(i32, i32)> = (self.entities, &*self.positions, self.teams)
+            .join()
+            .filter(|(entity, _, team)| team.arena == arena && self.is_alive(*entity))
+            .map(|(_, position, _)| (position.x, position.y))
+            .collect();
+        for event in self.events.iter() {
+            if let CombatEvent::Summoned { caster, position, .. } = event {
+                let same_arena = (self.chess, self.teams).join().any(|(chess, team)| chess.id == *caster && team.arena == arena);
+                if same_arena {
+                    occupied.push(*position);
+                }
+            }
+        }
+
+        // 雙方棋盤合起來的戰鬥場地，見 game_state::arena_position
+        let mut best: Option<(f32, Position)> = None;
+        for y in 0..2 * BOARD_HEIGHT {
+            for x in 0..BOARD_WIDTH {
+                if occupied.contains(&(x, y)) {
+                    continue;
+                }
+                let cell = Position { x, y };
+                let d = distance(wanted, &cell);
+                if best.as_ref().map_or(true, |(best_d, _)| d < *best_d) {
+                    best = Some((d, cell));
+                }
+            }
+        }
+        best.map(|(_, cell)| cell)
+    }
+
+    // 寫入一筆自訂的戰鬥紀錄
+    pub fn log(&mut self, message: impl Into<String>) {
+        let unit = self.unit_id(self.caster);
+        self.events.push(CombatEvent::Custom { unit, message: message.into() });
+    }
+}
+
+// 一次傷害的判定結果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HitRoll {
+    Dodged,
+    Hit { amount: i32, crit: bool }, // amount 為減免前的傷害，暴擊時已乘上暴擊倍率
+}
+
+// 以模擬亂數判定閃避（只有普攻可以被閃避）與暴擊。機率為 0 時不擲骰，
+// 沒有這些屬性的戰鬥使用的亂數與之前相同，原有的種子仍重現相同的結果
+pub fn roll_hit(amount: i32, attacker: &HitStats, target: &HitStats, dodgeable: bool, rng: &mut SimRng) -> HitRoll {
+    if dodgeable && target.dodge_chance > 0.0 && rng.gen_bool(target.dodge_chance as f64) {
+        return HitRoll::Dodged;
+    }
+    let crit = attacker.crit_chance > 0.0 && rng.gen_bool(attacker.crit_chance as f64);
+    let amount = if crit { (amount.max(0) as f32 * attacker.crit_damage) as i32 } else { amount };
+    HitRoll::Hit { amount, crit }
+}
+
+// 依實際造成的傷害與吸血比例回復存活棋子的生命，不超過最大生命值，回傳回復量
+pub fn vamp(stats: &mut CombatStats, dealt: i32, ratio: f32) -> i32 {
+    if stats.hp <= 0 || ratio <= 0.0 {
+        return 0;
+    }
+    let healed = ((dealt.max(0) as f32 * ratio) as i32).min(stats.max_hp - stats.hp).max(0);
+    stats.hp += healed;
+    healed
+}
+
+// 依傷害類型與目標含增益的防禦或魔抗計算減免後的傷害
+pub fn mitigate(amount: i32, kind: DamageKind, target: &HitStats) -> i32 {
+    let reduction = match kind {
+        DamageKind::Physical => target.defense,
+        DamageKind::Magic => target.magic_resist,
+        DamageKind::True => 0,
+    };
+    let reduction = reduction.clamp(0, 100) as f32 / 100.0;
+    (amount.max(0) as f32 * (1.0 - reduction)) as i32
+}
+
+pub fn distance(a: &Position, b: &Position) -> f32 {
+    let dx = a.x - b.x;
+    let dy = a.y - b.y;
+    ((dx * dx + dy * dy) as f32).sqrt()
+}
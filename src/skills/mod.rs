@@ -2,23 +2,62 @@ mod whirlwind_slash;
 mod fireball;
 mod multi_shot;
 mod shield_bash;
+mod context;
 
 pub use whirlwind_slash::WhirlwindSlash;
 pub use fireball::Fireball;
 pub use multi_shot::MultiShot;
 pub use shield_bash::ShieldBash;
+pub use context::{SkillContext, HitRoll, mitigate, distance, roll_hit, vamp};
 
-use specs::{Entity, WriteStorage, ReadStorage, Entities};
-use crate::{Position, CombatStats, StatusEffects};
-
-pub trait SkillExecutor {
-    fn execute(
-        &self,
-        caster: Entity,
-        damage: i32,
-        entities: &Entities,
-        positions: &ReadStorage<Position>,
-        combat_stats: &mut WriteStorage<CombatStats>,
-        status_effects: &mut WriteStorage<StatusEffects>,
-    );
-} 
\ No newline at end of file
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// 技能執行器，透過 SkillContext 查詢與修改戰鬥狀態
+pub trait SkillExecutor: Send + Sync {
+    fn execute(&self, ctx: &mut SkillContext);
+}
+
+// 技能 id 對應的執行器，啟動時註冊一次後放入 world 作為資源。
+// 新技能只要實作 SkillExecutor 並呼叫 register，不需要修改 CombatSystem
+#[derive(Clone, Default)]
+pub struct SkillRegistry {
+    executors: BTreeMap<String, Arc<dyn SkillExecutor>>,
+}
+
+impl SkillRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 內建的四種技能
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register("whirlwind_slash", WhirlwindSlash)
+            .register("fireball", Fireball)
+            .register("multi_shot", MultiShot)
+            .register("shield_bash", ShieldBash);
+        registry
+    }
+
+    // 註冊技能執行器；同一個 id 重複註冊是程式錯誤
+    pub fn register<S: SkillExecutor + 'static>(&mut self, id: &str, executor: S) -> &mut Self {
+        if self.executors.insert(id.to_string(), Arc::new(executor)).is_some() {
+            panic!("skill {:?} registered twice", id);
+        }
+        self
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn SkillExecutor> {
+        self.executors.get(id).map(|executor| executor.as_ref())
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.executors.contains_key(id)
+    }
+
+    pub fn ids(&self) -> Vec<&str> {
+        self.executors.keys().map(|id| id.as_str()).collect()
+    }
+}
@@ -0,0 +1,196 @@
+use specs::{System, ReadStorage, Join, Read, ReadExpect};
+use futures_util::SinkExt;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message;
+use crate::{Chess, CombatStats, Position, Owner, Bench};
+use crate::items::Equipment;
+use crate::rounds::Matchups;
+use crate::carousel::Carousel;
+use crate::combat_log::CombatLog;
+use crate::turn::{Player, TurnState};
+use crate::view::{self, PhaseTimer, PlayerSnapshot, PlayerDelta, ViewMessage};
+use crate::synergy::Synergies;
+use crate::server::{self, DisconnectReason, Heartbeat, WsRead, WsWrite};
+
+// 一個 tick 的觀戰畫面與戰鬥紀錄，經過相同的延遲後送出
+#[derive(Clone)]
+pub struct SpectatorFrame {
+    pub snapshot: Arc<PlayerSnapshot>,
+    pub combat_log: Option<Arc<CombatLog>>,
+}
+
+// 模擬端每個 tick 發布觀戰畫面，所有觀戰連線共用
+#[derive(Clone)]
+pub struct SpectatorFeed {
+    tx: broadcast::Sender<SpectatorFrame>,
+    delay: Duration,  // 觀戰延遲，避免透過觀戰偷看對手配置
+}
+
+impl SpectatorFeed {
+    pub fn new(delay: Duration) -> Self {
+        let (tx, _) = broadcast::channel(64);
+        SpectatorFeed { tx, delay }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SpectatorFrame> {
+        self.tx.subscribe()
+    }
+
+    pub fn has_spectators(&self) -> bool {
+        self.tx.receiver_count() > 0
+    }
+}
+
+// 建立所有玩家公開資訊的畫面並發布給觀戰者
+pub struct SpectatorSystem;
+
+impl<'a> System<'a> for SpectatorSystem {
+    type SystemData = (
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Chess>,
+        ReadStorage<'a, Owner>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Bench>,
+        ReadStorage<'a, Equipment>,
+        ReadStorage<'a, TurnState>,
+        Read<'a, PhaseTimer>,
+        Read<'a, Synergies>,
+        Read<'a, Matchups>,
+        Read<'a, Carousel>,
+        Read<'a, CombatLog>,
+        ReadExpect<'a, SpectatorFeed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (players, chess, owners, combat_stats, positions, benches, equipment, turn_states, phase_timer, synergies, matchups, carousel, combat_log, feed) = data;
+        if !feed.has_spectators() {
+            return;
+        }
+
+        let snapshot = view::build_snapshot(
+            None,
+            &players,
+            &chess,
+            &owners,
+            &combat_stats,
+            &positions,
+            &benches,
+            &equipment,
+            (&turn_states).join().next(),
+            &phase_timer,
+            &synergies,
+            &matchups,
+            &carousel,
+        );
+        // 沒有訂閱者時送出失敗，忽略即可
+        let _ = feed.tx.send(SpectatorFrame {
+            snapshot: Arc::new(snapshot),
+            combat_log: (!combat_log.events.is_empty()).then(|| Arc::new(combat_log.clone())),
+        });
+    }
+}
+
+// 觀戰連線：依延遲送出快照與差異，觀戰者送來的指令一律拒絕（resync 除外）
+pub async fn run_spectator(
+    mut write: WsWrite,
+    mut read: WsRead,
+    mut heartbeat: Heartbeat,
+    feed: SpectatorFeed,
+) {
+    let mut rx = feed.subscribe();
+    let mut pending: VecDeque<(Instant, SpectatorFrame)> = VecDeque::new();
+    let mut last: Option<Arc<PlayerSnapshot>> = None;
+    let mut seq: u64 = 0;
+
+    let reply = serde_json::json!({ "type": "spectating", "delay_secs": feed.delay.as_secs_f32() });
+    if write.send(Message::text(reply.to_string())).await.is_err() {
+        return;
+    }
+
+    let reason = loop {
+        let release_at = pending.front().map(|(received, _)| *received + feed.delay);
+
+        tokio::select! {
+            frame = rx.recv() => {
+                match frame {
+                    Ok(frame) => pending.push_back((Instant::now(), frame)),
+                    // 落後太多時丟掉舊畫面，下次送完整快照
+                    Err(broadcast::error::RecvError::Lagged(_)) => last = None,
+                    Err(broadcast::error::RecvError::Closed) => break DisconnectReason::Closed,
+                }
+            }
+            _ = tokio::time::sleep_until(release_at.unwrap_or_else(Instant::now)), if release_at.is_some() => {
+                let (_, SpectatorFrame { snapshot, combat_log }) = pending.pop_front().unwrap();
+                let text = match &last {
+                    Some(last) => {
+                        let delta = PlayerDelta::between(last, &snapshot);
+                        if delta.is_empty() {
+                            None
+                        } else {
+                            seq += 1;
+                            Some(serde_json::to_string(&ViewMessage::Delta { seq, delta: &delta }))
+                        }
+                    }
+                    None => {
+                        seq += 1;
+                        Some(serde_json::to_string(&ViewMessage::Snapshot { seq, snapshot: &snapshot }))
+                    }
+                };
+                match text {
+                    Some(Ok(text)) => {
+                        if let Err(e) = write.send(Message::text(text)).await {
+                            break DisconnectReason::Error(e.to_string());
+                        }
+                        last = Some(snapshot);
+                    }
+                    Some(Err(e)) => eprintln!("Failed to serialize spectator view: {}", e),
+                    None => {}
+                }
+                if let Some(text) = combat_log.and_then(|combat_log| {
+                    seq += 1;
+                    combat_log.to_message(seq)
+                }) {
+                    if let Err(e) = write.send(Message::text(text)).await {
+                        break DisconnectReason::Error(e.to_string());
+                    }
+                }
+            }
+            incoming = server::next_text(&mut read) => {
+                heartbeat.touch();
+                let text = match incoming {
+                    Ok(Some(text)) => text,
+                    Ok(None) => continue,
+                    Err(reason) => break reason,
+                };
+                let is_resync = serde_json::from_str::<serde_json::Value>(&text)
+                    .ok()
+                    .and_then(|v| v.get("type").and_then(|t| t.as_str()).map(|t| t == "resync"))
+                    .unwrap_or(false);
+                if is_resync {
+                    last = None;
+                    continue;
+                }
+                let reply = serde_json::json!({ "type": "error", "reason": "spectators cannot send commands" });
+                if let Err(e) = write.send(Message::text(reply.to_string())).await {
+                    break DisconnectReason::Error(e.to_string());
+                }
+            }
+            _ = heartbeat.tick() => {
+                if heartbeat.timed_out() {
+                    break DisconnectReason::TimedOut;
+                }
+                if let Err(e) = write.send(Message::Ping(Default::default())).await {
+                    break DisconnectReason::Error(e.to_string());
+                }
+            }
+        }
+    };
+
+    println!("觀戰連線已中斷: {:?}", reason);
+    let _ = write.close().await;
+}
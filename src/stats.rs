@@ -0,0 +1,97 @@
+use serde::{Serialize, Deserialize};
+use crate::{CombatStats, StatusEffects, EffectType};
+use crate::units::UnitDefinition;
+
+// 加在基礎屬性上的數值，來源可以是羈絆、裝備或增益效果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatModifiers {
+    pub hp: i32,
+    pub attack: i32,
+    pub defense: i32,
+    pub magic_resist: i32,
+    pub attack_speed: f32,
+    pub attack_range: f32,
+    pub max_mana: i32,
+    pub skill_damage: i32,
+    pub crit_chance: f32,
+    pub crit_damage: f32,
+    pub dodge_chance: f32,
+    pub lifesteal: f32,
+    pub spell_vamp: f32,
+}
+
+impl StatModifiers {
+    pub fn add(&mut self, other: &StatModifiers) {
+        self.hp += other.hp;
+        self.attack += other.attack;
+        self.defense += other.defense;
+        self.magic_resist += other.magic_resist;
+        self.attack_speed += other.attack_speed;
+        self.attack_range += other.attack_range;
+        self.max_mana += other.max_mana;
+        self.skill_damage += other.skill_damage;
+        self.crit_chance += other.crit_chance;
+        self.crit_damage += other.crit_damage;
+        self.dodge_chance += other.dodge_chance;
+        self.lifesteal += other.lifesteal;
+        self.spell_vamp += other.spell_vamp;
+    }
+
+    pub fn apply_to(&self, stats: &mut CombatStats) {
+        stats.max_hp = (stats.max_hp + self.hp).max(1);
+        stats.hp = stats.max_hp;
+        stats.attack = (stats.attack + self.attack).max(0);
+        stats.defense += self.defense;
+        stats.magic_resist += self.magic_resist;
+        stats.attack_speed = (stats.attack_speed + self.attack_speed).max(0.1);
+        stats.attack_range = (stats.attack_range + self.attack_range).max(1.0);
+        stats.max_mana = (stats.max_mana + self.max_mana).max(1);
+        stats.skill.damage = (stats.skill.damage + self.skill_damage).max(0);
+        stats.crit_chance = (stats.crit_chance + self.crit_chance).clamp(0.0, 1.0);
+        stats.crit_damage = (stats.crit_damage + self.crit_damage).max(1.0);
+        stats.dodge_chance = (stats.dodge_chance + self.dodge_chance).clamp(0.0, 1.0);
+        stats.lifesteal = (stats.lifesteal + self.lifesteal).max(0.0);
+        stats.spell_vamp = (stats.spell_vamp + self.spell_vamp).max(0.0);
+    }
+}
+
+// 傷害計算使用的屬性，已加上狀態效果的增益，在每次傷害時計算
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitStats {
+    pub attack: i32,
+    pub defense: i32,
+    pub magic_resist: i32,
+    pub crit_chance: f32,
+    pub crit_damage: f32,
+    pub dodge_chance: f32,
+    pub lifesteal: f32,
+    pub spell_vamp: f32,
+}
+
+impl HitStats {
+    pub fn of(stats: &CombatStats, effects: Option<&StatusEffects>) -> Self {
+        let buff = |effect_type: EffectType| -> f32 {
+            effects.map_or(0.0, |effects| {
+                effects.effects.iter().filter(|e| e.effect_type == effect_type && e.duration > 0.0).map(|e| e.magnitude).sum()
+            })
+        };
+        HitStats {
+            attack: (stats.attack + buff(EffectType::AttackBuff).round() as i32).max(0),
+            defense: stats.defense + buff(EffectType::DefenseBuff).round() as i32,
+            magic_resist: stats.magic_resist,
+            crit_chance: (stats.crit_chance + buff(EffectType::CritBuff)).clamp(0.0, 1.0),
+            crit_damage: stats.crit_damage,
+            dodge_chance: (stats.dodge_chance + buff(EffectType::DodgeBuff)).clamp(0.0, 1.0),
+            lifesteal: (stats.lifesteal + buff(EffectType::LifestealBuff)).max(0.0),
+            spell_vamp: (stats.spell_vamp + buff(EffectType::SpellVampBuff)).max(0.0),
+        }
+    }
+}
+
+// 戰鬥使用的實際屬性：定義與星級決定的基礎值，加上所有加成
+pub fn effective_stats(definition: &UnitDefinition, level: u32, modifiers: &StatModifiers) -> CombatStats {
+    let mut stats = definition.combat_stats(level);
+    modifiers.apply_to(&mut stats);
+    stats
+}
@@ -0,0 +1,198 @@
+use serde::{Serialize, Deserialize};
+use specs::{ReadStorage, Join};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use crate::{Chess, Owner, Position, Effect};
+use crate::data::{self, DataError};
+use crate::stats::StatModifiers;
+use crate::units::{UnitDefinition, UnitDefinitions};
+
+// 羈絆加成的作用對象
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraitScope {
+    Members, // 只有擁有此羈絆的棋子
+    Team,    // 場上所有友方棋子
+}
+
+impl Default for TraitScope {
+    fn default() -> Self {
+        TraitScope::Members
+    }
+}
+
+// 場上達到 count 個不同棋子時啟動的階段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TraitTier {
+    pub count: usize,
+    #[serde(default)]
+    pub stats: StatModifiers,
+    #[serde(default)]
+    pub effect: Option<Effect>, // 戰鬥開始時附加的狀態效果
+}
+
+// 種族或職業
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TraitDefinition {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub scope: TraitScope,
+    pub tiers: Vec<TraitTier>, // 依 count 由小到大排列
+}
+
+impl TraitDefinition {
+    // 目前數量可啟動的最高階段
+    pub fn active_tier(&self, count: usize) -> Option<(usize, &TraitTier)> {
+        self.tiers
+            .iter()
+            .enumerate()
+            .filter(|(_, tier)| tier.count <= count)
+            .last()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty() {
+            return Err("id must not be empty".to_string());
+        }
+        if self.tiers.is_empty() {
+            return Err("at least one tier is required".to_string());
+        }
+        if self.tiers[0].count == 0 {
+            return Err("tier count must be at least 1".to_string());
+        }
+        if self.tiers.windows(2).any(|pair| pair[0].count >= pair[1].count) {
+            return Err("tier counts must be strictly increasing".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TraitDefinitions {
+    traits: BTreeMap<String, TraitDefinition>,
+}
+
+impl TraitDefinitions {
+    pub fn load_dir(dir: &Path) -> Result<Self, DataError> {
+        let mut traits: BTreeMap<String, TraitDefinition> = BTreeMap::new();
+        let mut sources: BTreeMap<String, PathBuf> = BTreeMap::new();
+
+        for (path, definition) in data::load_json_dir::<TraitDefinition>(dir)? {
+            if let Some(first) = sources.get(&definition.id) {
+                return Err(DataError::Duplicate {
+                    id: definition.id.clone(),
+                    first: first.clone(),
+                    second: path,
+                });
+            }
+            definition.validate().map_err(|reason| DataError::Invalid {
+                path: path.clone(),
+                id: definition.id.clone(),
+                reason,
+            })?;
+            sources.insert(definition.id.clone(), path);
+            traits.insert(definition.id.clone(), definition);
+        }
+
+        Ok(TraitDefinitions { traits })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TraitDefinition> {
+        self.traits.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.traits.contains_key(id)
+    }
+
+    pub fn definitions(&self) -> &BTreeMap<String, TraitDefinition> {
+        &self.traits
+    }
+}
+
+// 某位玩家的一個羈絆狀態，會回報給客戶端
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActiveTrait {
+    pub trait_id: String,
+    pub count: usize,
+    pub tier: Option<usize>, // 已啟動的階段索引，未達最低數量時為 None
+}
+
+// 每位玩家在最近一次戰鬥開始時的羈絆，作為 world 的資源
+#[derive(Debug, Default)]
+pub struct Synergies {
+    pub by_player: BTreeMap<usize, Vec<ActiveTrait>>,
+}
+
+impl Synergies {
+    pub fn for_player(&self, player_id: usize) -> Vec<ActiveTrait> {
+        self.by_player.get(&player_id).cloned().unwrap_or_default()
+    }
+}
+
+// 計算每位玩家場上（不含備戰席）不同棋子的羈絆數量，同一種棋子只算一次
+pub fn count_traits(
+    chess: &ReadStorage<Chess>,
+    owners: &ReadStorage<Owner>,
+    positions: &ReadStorage<Position>,
+    unit_definitions: &UnitDefinitions,
+    trait_definitions: &TraitDefinitions,
+) -> Synergies {
+    let mut fielded: BTreeMap<usize, BTreeSet<String>> = BTreeMap::new();
+    for (chess, owner, _) in (chess, owners, positions).join() {
+        fielded.entry(owner.player_id).or_default().insert(chess.unit_id.clone());
+    }
+
+    let mut synergies = Synergies::default();
+    for (player_id, unit_ids) in fielded {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for definition in unit_ids.iter().filter_map(|id| unit_definitions.get(id)) {
+            for trait_id in &definition.traits {
+                *counts.entry(trait_id.clone()).or_default() += 1;
+            }
+        }
+
+        let active = counts
+            .into_iter()
+            .map(|(trait_id, count)| ActiveTrait {
+                tier: trait_definitions
+                    .get(&trait_id)
+                    .and_then(|definition| definition.active_tier(count))
+                    .map(|(index, _)| index),
+                trait_id,
+                count,
+            })
+            .collect();
+        synergies.by_player.insert(player_id, active);
+    }
+    synergies
+}
+
+// 某個棋子從所屬玩家已啟動的羈絆得到的屬性加成與戰鬥開始時的狀態效果
+pub fn trait_bonuses(
+    active_traits: &[ActiveTrait],
+    unit: &UnitDefinition,
+    trait_definitions: &TraitDefinitions,
+) -> (StatModifiers, Vec<Effect>) {
+    let mut modifiers = StatModifiers::default();
+    let mut effects = Vec::new();
+
+    for active in active_traits {
+        let definition = match trait_definitions.get(&active.trait_id) {
+            Some(definition) => definition,
+            None => continue,
+        };
+        let tier = match active.tier.and_then(|index| definition.tiers.get(index)) {
+            Some(tier) => tier,
+            None => continue,
+        };
+        if definition.scope == TraitScope::Team || unit.traits.contains(&active.trait_id) {
+            modifiers.add(&tier.stats);
+            effects.extend(tier.effect.clone());
+        }
+    }
+    (modifiers, effects)
+}
@@ -0,0 +1,126 @@
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+use specs::{Component, DenseVecStorage, Entity};
+use crate::rng::SimRng;
+
+// 選擇攻擊或技能目標的策略，在棋子定義中設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetPolicy {
+    #[default]
+    Nearest,
+    LowestHp,
+    HighestAttack,
+    Furthest,
+    Random,
+    Sticky, // 持續攻擊目前的目標，直到目標死亡或離開範圍，之後改選最近的敵人
+}
+
+// 棋子目前的普攻目標，每場戰鬥開始時清除
+#[derive(Component, Debug, Clone, Copy)]
+#[storage(DenseVecStorage)]
+pub struct CurrentTarget {
+    pub target: Entity,
+}
+
+// 範圍內的一個候選目標
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate {
+    pub entity: Entity,
+    pub distance: f32,
+    pub hp: i32,
+    pub attack: i32,
+}
+
+// 依策略排列候選目標，第一個即為選中的目標。條件相同時依距離由近到遠、再依 entity id，
+// 讓相同的戰鬥狀態總是選出相同的目標；random 只使用模擬亂數
+pub fn rank(policy: TargetPolicy, mut candidates: Vec<Candidate>, current: Option<Entity>, rng: &mut SimRng) -> Vec<Entity> {
+    candidates.sort_by(|a, b| a.distance.total_cmp(&b.distance).then(a.entity.id().cmp(&b.entity.id())));
+    match policy {
+        TargetPolicy::Nearest => {}
+        TargetPolicy::LowestHp => candidates.sort_by_key(|c| c.hp),
+        TargetPolicy::HighestAttack => candidates.sort_by_key(|c| std::cmp::Reverse(c.attack)),
+        TargetPolicy::Furthest => {
+            candidates.sort_by(|a, b| b.distance.total_cmp(&a.distance).then(a.entity.id().cmp(&b.entity.id())))
+        }
+        TargetPolicy::Random => candidates.shuffle(rng),
+        TargetPolicy::Sticky => {
+            if let Some(index) = candidates.iter().position(|c| Some(c.entity) == current) {
+                let sticky = candidates.remove(index);
+                candidates.insert(0, sticky);
+            }
+        }
+    }
+    candidates.into_iter().map(|c| c.entity).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, World, WorldExt};
+
+    fn entities(count: usize) -> Vec<Entity> {
+        let mut world = World::new();
+        (0..count).map(|_| world.create_entity().build()).collect()
+    }
+
+    fn candidate(entity: Entity, distance: f32, hp: i32, attack: i32) -> Candidate {
+        Candidate { entity, distance, hp, attack }
+    }
+
+    fn ranked(policy: TargetPolicy, candidates: Vec<Candidate>, current: Option<Entity>) -> Vec<Entity> {
+        rank(policy, candidates, current, &mut SimRng::seeded(1))
+    }
+
+    #[test]
+    fn nearest_breaks_distance_ties_by_entity_id() {
+        let e = entities(3);
+        let candidates = vec![candidate(e[2], 1.0, 50, 10), candidate(e[1], 2.0, 50, 10), candidate(e[0], 1.0, 50, 10)];
+        assert_eq!(ranked(TargetPolicy::Nearest, candidates, None), vec![e[0], e[2], e[1]]);
+    }
+
+    #[test]
+    fn lowest_hp_and_highest_attack_break_ties_by_distance_then_entity_id() {
+        let e = entities(4);
+        let candidates = vec![
+            candidate(e[3], 1.0, 30, 20),
+            candidate(e[2], 3.0, 30, 20),
+            candidate(e[1], 1.0, 30, 20),
+            candidate(e[0], 2.0, 80, 5),
+        ];
+        assert_eq!(ranked(TargetPolicy::LowestHp, candidates.clone(), None), vec![e[1], e[3], e[2], e[0]]);
+        assert_eq!(ranked(TargetPolicy::HighestAttack, candidates, None), vec![e[1], e[3], e[2], e[0]]);
+    }
+
+    #[test]
+    fn furthest_breaks_distance_ties_by_entity_id() {
+        let e = entities(3);
+        let candidates = vec![candidate(e[2], 3.0, 50, 10), candidate(e[0], 1.0, 50, 10), candidate(e[1], 3.0, 50, 10)];
+        assert_eq!(ranked(TargetPolicy::Furthest, candidates, None), vec![e[1], e[2], e[0]]);
+    }
+
+    #[test]
+    fn random_depends_only_on_the_seed_not_the_candidate_order() {
+        let e = entities(6);
+        let candidates: Vec<Candidate> = e.iter().map(|&entity| candidate(entity, 1.0, 50, 10)).collect();
+        let mut reversed = candidates.clone();
+        reversed.reverse();
+
+        let first = ranked(TargetPolicy::Random, candidates, None);
+        assert_eq!(ranked(TargetPolicy::Random, reversed, None), first);
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(sorted, e);
+    }
+
+    #[test]
+    fn sticky_keeps_the_current_target_while_it_is_in_range() {
+        let e = entities(3);
+        let in_range = vec![candidate(e[0], 1.0, 50, 10), candidate(e[1], 2.0, 50, 10), candidate(e[2], 3.0, 50, 10)];
+        assert_eq!(ranked(TargetPolicy::Sticky, in_range.clone(), Some(e[2]))[0], e[2]);
+
+        // 目前的目標離開範圍後不再是候選目標，改選最近的敵人
+        let out_of_range: Vec<Candidate> = in_range.into_iter().filter(|c| c.entity != e[2]).collect();
+        assert_eq!(ranked(TargetPolicy::Sticky, out_of_range, Some(e[2])), vec![e[0], e[1]]);
+    }
+}
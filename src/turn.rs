@@ -1,6 +1,5 @@
-use specs::{Component, System, VecStorage, WriteStorage, ReadStorage, Join, Entity};
+use specs::{Component, VecStorage};
 use serde::{Serialize, Deserialize};
-use crate::ChannelMessage;
 
 // 回合狀態組件
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +17,7 @@ pub enum TurnPhase {
     Preparation,    // 準備階段：玩家可以購買、升級、放置棋子
     Combat,         // 戰鬥階段：棋子自動戰鬥
     Resolution,     // 結算階段：結算傷害、獎勵等
+    Carousel,       // 共享輪抽：血量最低的玩家先選棋子
 }
 
 // 玩家組件
@@ -30,42 +30,10 @@ pub struct Player {
     pub gold: i32,
     pub level: u32,
     pub experience: u32,
-}
-
-// 回合系統
-pub struct TurnSystem;
-
-impl<'a> System<'a> for TurnSystem {
-    type SystemData = (
-        WriteStorage<'a, TurnState>,
-        ReadStorage<'a, Player>,
-    );
-
-    fn run(&mut self, (mut turn_states, players): Self::SystemData) {
-        // 只應該有一個回合狀態實體
-        if let Some(turn_state) = (&mut turn_states).join().next() {
-            match turn_state.current_phase {
-                TurnPhase::Preparation => {
-                    // 準備階段結束後轉入戰鬥階段
-                    turn_state.current_phase = TurnPhase::Combat;
-                }
-                TurnPhase::Combat => {
-                    // 戰鬥階段結束後轉入結算階段
-                    turn_state.current_phase = TurnPhase::Resolution;
-                }
-                TurnPhase::Resolution => {
-                    // 結算階段結束後，進入下一個玩家的準備階段
-                    turn_state.current_phase = TurnPhase::Preparation;
-                    turn_state.current_player = (turn_state.current_player + 1) % turn_state.total_players;
-                    
-                    // 如果回到第一個玩家，增加回合數
-                    if turn_state.current_player == 0 {
-                        turn_state.turn_number += 1;
-                    }
-                }
-            }
-        }
-    }
+    pub shop: Vec<String>, // 商店目前提供的棋子 id
+    pub items: Vec<String>, // 尚未裝備的道具 id
+    pub connected: bool,  // WebSocket 是否在線，斷線期間棋子照常自動戰鬥
+    pub abandoned: bool,  // 斷線超過寬限時間，不可再重連，下一次配對前被淘汰
 }
 
 // 回合管理器
@@ -73,15 +41,17 @@ pub struct TurnManager {
     preparation_time: f32,    // 準備階段時間（秒）
     combat_time: f32,        // 戰鬥階段時間（秒）
     resolution_time: f32,    // 結算階段時間（秒）
+    carousel_time: f32,      // 輪抽階段最長時間（秒），所有人選完會提早結束
     current_time: f32,       // 當前階段已經過的時間
 }
 
 impl TurnManager {
-    pub fn new(preparation_time: f32, combat_time: f32, resolution_time: f32) -> Self {
+    pub fn new(preparation_time: f32, combat_time: f32, resolution_time: f32, carousel_time: f32) -> Self {
         Self {
             preparation_time,
             combat_time,
             resolution_time,
+            carousel_time,
             current_time: 0.0,
         }
     }
@@ -93,6 +63,7 @@ impl TurnManager {
             TurnPhase::Preparation => self.preparation_time,
             TurnPhase::Combat => self.combat_time,
             TurnPhase::Resolution => self.resolution_time,
+            TurnPhase::Carousel => self.carousel_time,
         };
 
         if self.current_time >= phase_time {
@@ -103,11 +74,18 @@ impl TurnManager {
         }
     }
 
+    // 提早結束目前階段，重新計時下一個階段
+    pub fn reset(&mut self) {
+        self.current_time = 0.0;
+    }
+
+    // 目前階段剩餘時間（秒）
     pub fn get_remaining_time(&self, turn_state: &TurnState) -> f32 {
         let phase_time = match turn_state.current_phase {
             TurnPhase::Preparation => self.preparation_time,
             TurnPhase::Combat => self.combat_time,
             TurnPhase::Resolution => self.resolution_time,
+            TurnPhase::Carousel => self.carousel_time,
         };
         phase_time - self.current_time
     }
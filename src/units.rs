@@ -0,0 +1,247 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::{CombatStats, Skill};
+use crate::data::{self, DataError};
+use crate::targeting::TargetPolicy;
+
+// 棋子的基礎屬性（1 星）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BaseStats {
+    pub hp: i32,
+    pub attack: i32,
+    pub defense: i32,
+    pub magic_resist: i32,
+    pub attack_speed: f32,
+    pub attack_range: f32,
+    pub max_mana: i32,
+    #[serde(default)]
+    pub crit_chance: f32,
+    #[serde(default = "default_crit_damage")]
+    pub crit_damage: f32,
+    #[serde(default)]
+    pub dodge_chance: f32,
+    #[serde(default)]
+    pub lifesteal: f32,
+    #[serde(default)]
+    pub spell_vamp: f32,
+}
+
+// 未設定時暴擊造成 1.5 倍傷害
+pub fn default_crit_damage() -> f32 {
+    1.5
+}
+
+// 每升一星的倍率，例如 hp 1.8 表示 2 星為 1.8 倍、3 星為 1.8 * 1.8 倍
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LevelScaling {
+    pub hp: f32,
+    pub attack: f32,
+    pub skill_damage: f32,
+}
+
+impl Default for LevelScaling {
+    fn default() -> Self {
+        LevelScaling {
+            hp: 1.8,
+            attack: 1.8,
+            skill_damage: 1.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillDefinition {
+    pub id: String, // 必須是 SkillRegistry 中已註冊的技能
+    pub damage: i32,
+    pub range: f32,
+    #[serde(default)]
+    pub duration: Option<f32>, // 持續性效果的持續時間（秒）
+    pub cooldown: f32, // 冷卻時間（秒）
+    #[serde(default = "default_requires_mana")]
+    pub requires_mana: bool, // false 表示只要冷卻結束就會施放
+    #[serde(default)]
+    pub cast_time: f32, // 施法前搖（秒），0 表示立即施放
+    #[serde(default)]
+    pub targeting: Option<TargetPolicy>, // 未設定時與棋子的普攻相同
+}
+
+fn default_requires_mana() -> bool {
+    true
+}
+
+// 資料檔中的一個棋子定義
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnitDefinition {
+    pub id: String,
+    pub name: String,        // 顯示名稱
+    pub cost: u32,           // 費用階級 1~5
+    pub stats: BaseStats,
+    #[serde(default)]
+    pub scaling: LevelScaling,
+    pub skill: SkillDefinition,
+    #[serde(default)]
+    pub traits: Vec<String>, // 種族與職業
+    #[serde(default)]
+    pub targeting: TargetPolicy, // 普攻選擇目標的策略，預設為最近的敵人
+}
+
+pub const MAX_COST: u32 = 5;
+
+impl UnitDefinition {
+    // 依星級計算戰鬥屬性
+    pub fn combat_stats(&self, level: u32) -> CombatStats {
+        let steps = level.saturating_sub(1) as i32;
+        let scale = |value: i32, factor: f32| (value as f32 * factor.powi(steps)).round() as i32;
+        let hp = scale(self.stats.hp, self.scaling.hp);
+
+        CombatStats {
+            name: self.name.clone(),
+            hp,
+            max_hp: hp,
+            attack: scale(self.stats.attack, self.scaling.attack),
+            defense: self.stats.defense,
+            magic_resist: self.stats.magic_resist,
+            attack_speed: self.stats.attack_speed,
+            attack_cooldown: 0.0,
+            move_cooldown: 0.0,
+            attack_range: self.stats.attack_range,
+            mana: 0,
+            max_mana: self.stats.max_mana,
+            skill: Skill {
+                id: self.skill.id.clone(),
+                damage: scale(self.skill.damage, self.scaling.skill_damage),
+                range: self.skill.range,
+                duration: self.skill.duration,
+                cooldown: self.skill.cooldown,
+                current_cooldown: 0.0,
+                requires_mana: self.skill.requires_mana,
+                cast_time: self.skill.cast_time,
+                targeting: self.skill.targeting.unwrap_or(self.targeting),
+            },
+            targeting: self.targeting,
+            crit_chance: self.stats.crit_chance,
+            crit_damage: self.stats.crit_damage,
+            dodge_chance: self.stats.dodge_chance,
+            lifesteal: self.stats.lifesteal,
+            spell_vamp: self.stats.spell_vamp,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty() {
+            return Err("id must not be empty".to_string());
+        }
+        if self.cost == 0 || self.cost > MAX_COST {
+            return Err(format!("cost must be between 1 and {}", MAX_COST));
+        }
+        let stats = &self.stats;
+        if stats.hp <= 0 || stats.max_mana <= 0 {
+            return Err("hp and max_mana must be positive".to_string());
+        }
+        if stats.attack < 0 || stats.defense < 0 || stats.magic_resist < 0 {
+            return Err("attack, defense and magic_resist must not be negative".to_string());
+        }
+        if !(stats.attack_speed > 0.0 && stats.attack_range > 0.0) {
+            return Err("attack_speed and attack_range must be positive".to_string());
+        }
+        if ![stats.crit_chance, stats.dodge_chance].iter().all(|chance| (0.0..=1.0).contains(chance)) {
+            return Err("crit_chance and dodge_chance must be between 0 and 1".to_string());
+        }
+        if !(stats.crit_damage >= 1.0) {
+            return Err("crit_damage must be at least 1".to_string());
+        }
+        if !(stats.lifesteal >= 0.0 && stats.spell_vamp >= 0.0) {
+            return Err("lifesteal and spell_vamp must not be negative".to_string());
+        }
+        let scaling = &self.scaling;
+        if !(scaling.hp > 0.0 && scaling.attack > 0.0 && scaling.skill_damage > 0.0) {
+            return Err("scaling factors must be positive".to_string());
+        }
+        if !(self.skill.range > 0.0) {
+            return Err("skill range must be positive".to_string());
+        }
+        if self.skill.duration.map_or(false, |duration| !(duration.is_finite() && duration > 0.0)) {
+            return Err("skill duration must be positive".to_string());
+        }
+        if !(self.skill.cast_time.is_finite() && self.skill.cast_time >= 0.0) {
+            return Err("skill cast_time must not be negative".to_string());
+        }
+        if !(self.skill.cooldown.is_finite() && self.skill.cooldown >= 0.0) {
+            return Err("skill cooldown must not be negative".to_string());
+        }
+        if !self.skill.requires_mana && self.skill.cooldown <= 0.0 {
+            return Err("a skill that does not require mana needs a positive cooldown".to_string());
+        }
+        for (i, name) in self.traits.iter().enumerate() {
+            if name.is_empty() {
+                return Err("trait names must not be empty".to_string());
+            }
+            if self.traits[..i].contains(name) {
+                return Err(format!("trait {:?} listed twice", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+// 所有棋子定義，以 id 為鍵；作為 world 的資源
+#[derive(Debug, Clone, Default)]
+pub struct UnitDefinitions {
+    units: BTreeMap<String, UnitDefinition>,
+}
+
+impl UnitDefinitions {
+    // 讀取目錄下所有棋子定義檔並檢查內容
+    pub fn load_dir(dir: &Path) -> Result<Self, DataError> {
+        let mut units: BTreeMap<String, UnitDefinition> = BTreeMap::new();
+        let mut sources: BTreeMap<String, PathBuf> = BTreeMap::new();
+
+        for (path, unit) in data::load_json_dir::<UnitDefinition>(dir)? {
+            if let Some(first) = sources.get(&unit.id) {
+                return Err(DataError::Duplicate {
+                    id: unit.id.clone(),
+                    first: first.clone(),
+                    second: path,
+                });
+            }
+            unit.validate().map_err(|reason| DataError::Invalid {
+                path: path.clone(),
+                id: unit.id.clone(),
+                reason,
+            })?;
+            sources.insert(unit.id.clone(), path);
+            units.insert(unit.id.clone(), unit);
+        }
+
+        if units.is_empty() {
+            return Err(DataError::Invalid {
+                path: dir.to_path_buf(),
+                id: String::new(),
+                reason: "no unit definitions found".to_string(),
+            });
+        }
+
+        Ok(UnitDefinitions { units })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&UnitDefinition> {
+        self.units.get(id)
+    }
+
+    pub fn ids(&self) -> Vec<&str> {
+        self.units.keys().map(|id| id.as_str()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &UnitDefinition> {
+        self.units.values()
+    }
+
+    pub fn definitions(&self) -> &BTreeMap<String, UnitDefinition> {
+        &self.units
+    }
+}
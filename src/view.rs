@@ -0,0 +1,328 @@
+use specs::{System, ReadStorage, Join, Read, Write};
+use serde::Serialize;
+use uuid::Uuid;
+use std::collections::HashMap;
+use crate::{Chess, CombatStats, Position, Owner, Bench, ChannelMessage, WebsocketChannel};
+use crate::turn::{Player, TurnState, TurnPhase};
+use crate::synergy::{ActiveTrait, Synergies};
+use crate::items::Equipment;
+use crate::rounds::{Matchup, Matchups};
+use crate::carousel::{Carousel, CarouselView};
+use crate::combat_log::CombatLog;
+
+// 目前階段剩餘時間，由 GameState::update 從 TurnManager 寫入
+#[derive(Debug, Default)]
+pub struct PhaseTimer {
+    pub remaining_time: f32,
+}
+
+// 每位玩家已送出的序號與最後一次的畫面，用來計算差異
+#[derive(Debug, Default)]
+pub struct ViewState {
+    players: HashMap<usize, PlayerViewState>,
+}
+
+#[derive(Debug, Default)]
+struct PlayerViewState {
+    seq: u64,
+    last: Option<PlayerSnapshot>,
+}
+
+impl ViewState {
+    // 下一次 ViewSystem 執行時改送完整快照（加入、重連或客戶端要求重新同步）
+    pub fn request_snapshot(&mut self, player_id: usize) {
+        self.players.entry(player_id).or_default().last = None;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnitView {
+    pub id: Uuid,
+    pub owner: Option<usize>, // 野怪沒有擁有者
+    pub name: String,
+    pub level: u32,
+    pub unit_id: String,
+    pub hp: i32,
+    pub max_hp: i32,
+    pub mana: i32,
+    pub max_mana: i32,
+    pub position: Option<(i32, i32)>,
+    pub bench_slot: Option<usize>,
+    pub items: Vec<String>,
+}
+
+// 只有自己看得到的資訊
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrivatePlayerView {
+    pub id: usize,
+    pub gold: i32,
+    pub experience: u32,
+    pub shop: Vec<String>,
+    pub items: Vec<String>, // 尚未裝備的道具
+}
+
+// 所有玩家都看得到的資訊
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PublicPlayerView {
+    pub id: usize,
+    pub name_id: String,
+    pub health: i32,
+    pub level: u32,
+    pub connected: bool,
+    pub synergies: Vec<ActiveTrait>, // 最近一次戰鬥開始時的羈絆
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PhaseView {
+    pub phase: TurnPhase,
+    pub turn_number: u32,
+    pub remaining_secs: u32,
+}
+
+// 完整快照：自己的備戰席、棋盤、商店、金錢、等級，以及對手的公開資訊
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlayerSnapshot {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub you: Option<PrivatePlayerView>,
+    pub players: Vec<PublicPlayerView>,
+    pub units: Vec<UnitView>,
+    pub phase: Option<PhaseView>,
+    pub matchups: Vec<Matchup>, // 本回合的對戰組合與結算結果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carousel: Option<CarouselView>, // 只在輪抽階段出現
+}
+
+// 與上一次送出的畫面相比有變動的部分
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub you: Option<PrivatePlayerView>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub players: Vec<PublicPlayerView>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub units: Vec<UnitView>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed_units: Vec<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<PhaseView>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matchups: Option<Vec<Matchup>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carousel: Option<CarouselView>,
+}
+
+impl PlayerDelta {
+    pub fn between(old: &PlayerSnapshot, new: &PlayerSnapshot) -> Self {
+        let old_units: HashMap<Uuid, &UnitView> = old.units.iter().map(|u| (u.id, u)).collect();
+        let new_ids: Vec<Uuid> = new.units.iter().map(|u| u.id).collect();
+
+        PlayerDelta {
+            you: if old.you != new.you { new.you.clone() } else { None },
+            players: new
+                .players
+                .iter()
+                .filter(|p| !old.players.contains(p))
+                .cloned()
+                .collect(),
+            units: new
+                .units
+                .iter()
+                .filter(|u| old_units.get(&u.id) != Some(u))
+                .cloned()
+                .collect(),
+            removed_units: old
+                .units
+                .iter()
+                .map(|u| u.id)
+                .filter(|id| !new_ids.contains(id))
+                .collect(),
+            phase: if old.phase != new.phase { new.phase.clone() } else { None },
+            matchups: if old.matchups != new.matchups { Some(new.matchups.clone()) } else { None },
+            carousel: if old.carousel != new.carousel { new.carousel.clone() } else { None },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.you.is_none()
+            && self.players.is_empty()
+            && self.units.is_empty()
+            && self.removed_units.is_empty()
+            && self.phase.is_none()
+            && self.matchups.is_none()
+            && self.carousel.is_none()
+    }
+}
+
+// 送往客戶端的訊息，客戶端依 seq 判斷是否漏收，漏收時送 {"type": "resync"}
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ViewMessage<'a> {
+    Snapshot { seq: u64, #[serde(flatten)] snapshot: &'a PlayerSnapshot },
+    Delta { seq: u64, #[serde(flatten)] delta: &'a PlayerDelta },
+}
+
+// 建立指定玩家看到的畫面；對手的備戰席與商店不公開。viewer 為 None 時是觀戰者的畫面
+pub fn build_snapshot(
+    viewer: Option<&Player>,
+    players: &ReadStorage<Player>,
+    chess: &ReadStorage<Chess>,
+    owners: &ReadStorage<Owner>,
+    combat_stats: &ReadStorage<CombatStats>,
+    positions: &ReadStorage<Position>,
+    benches: &ReadStorage<Bench>,
+    equipment: &ReadStorage<Equipment>,
+    turn_state: Option<&TurnState>,
+    phase_timer: &PhaseTimer,
+    synergies: &Synergies,
+    matchups: &Matchups,
+    carousel: &Carousel,
+) -> PlayerSnapshot {
+    let mut public_players: Vec<PublicPlayerView> = players
+        .join()
+        .map(|p| PublicPlayerView {
+            id: p.id,
+            name_id: p.name_id.clone(),
+            health: p.health,
+            level: p.level,
+            connected: p.connected,
+            synergies: synergies.for_player(p.id),
+        })
+        .collect();
+    public_players.sort_by_key(|p| p.id);
+
+    let mut units: Vec<UnitView> = (chess, owners.maybe(), combat_stats, positions.maybe(), benches.maybe(), equipment.maybe())
+        .join()
+        .filter(|(_, owner, _, _, bench, _)| bench.is_none() || viewer.zip(*owner).map_or(false, |(v, o)| v.id == o.player_id))
+        .map(|(chess, owner, stats, position, bench, equipment)| UnitView {
+            id: chess.id,
+            owner: owner.map(|o| o.player_id),
+            name: chess.name.clone(),
+            level: chess.level,
+            unit_id: chess.unit_id.clone(),
+            hp: stats.hp,
+            max_hp: stats.max_hp,
+            mana: stats.mana,
+            max_mana: stats.max_mana,
+            position: position.map(|p| (p.x, p.y)),
+            bench_slot: bench.map(|b| b.slot),
+            items: equipment.map(|e| e.items.clone()).unwrap_or_default(),
+        })
+        .collect();
+    units.sort_by_key(|u| u.id);
+
+    PlayerSnapshot {
+        you: viewer.map(|viewer| PrivatePlayerView {
+            id: viewer.id,
+            gold: viewer.gold,
+            experience: viewer.experience,
+            shop: viewer.shop.clone(),
+            items: viewer.items.clone(),
+        }),
+        players: public_players,
+        units,
+        phase: turn_state.map(|turn_state| PhaseView {
+            phase: turn_state.current_phase,
+            turn_number: turn_state.turn_number,
+            remaining_secs: phase_timer.remaining_time.max(0.0).ceil() as u32,
+        }),
+        matchups: matchups.matchups.clone(),
+        carousel: if carousel.is_active() { Some(carousel.view()) } else { None },
+    }
+}
+
+// 每個 tick 對在線玩家送出完整快照或差異
+pub struct ViewSystem;
+
+impl<'a> System<'a> for ViewSystem {
+    type SystemData = (
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Chess>,
+        ReadStorage<'a, Owner>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Bench>,
+        ReadStorage<'a, Equipment>,
+        ReadStorage<'a, TurnState>,
+        Read<'a, PhaseTimer>,
+        Read<'a, Synergies>,
+        Read<'a, Matchups>,
+        Read<'a, Carousel>,
+        Read<'a, CombatLog>,
+        Write<'a, ViewState>,
+        Read<'a, HashMap<String, WebsocketChannel>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (players, chess, owners, combat_stats, positions, benches, equipment, turn_states, phase_timer, synergies, matchups, carousel, combat_log, mut view_state, websocket_channels) = data;
+        let turn_state = (&turn_states).join().next();
+
+        for player in (&players).join() {
+            if !player.connected {
+                continue;
+            }
+            let channel = match websocket_channels.get(&player.name_id) {
+                Some(channel) => channel,
+                None => continue,
+            };
+
+            let snapshot = build_snapshot(
+                Some(player),
+                &players,
+                &chess,
+                &owners,
+                &combat_stats,
+                &positions,
+                &benches,
+                &equipment,
+                turn_state,
+                &phase_timer,
+                &synergies,
+                &matchups,
+                &carousel,
+            );
+
+            let state = view_state.players.entry(player.id).or_default();
+            let text = match &state.last {
+                Some(last) => {
+                    let delta = PlayerDelta::between(last, &snapshot);
+                    if delta.is_empty() {
+                        continue;
+                    }
+                    state.seq += 1;
+                    serde_json::to_string(&ViewMessage::Delta { seq: state.seq, delta: &delta })
+                }
+                None => {
+                    state.seq += 1;
+                    serde_json::to_string(&ViewMessage::Snapshot { seq: state.seq, snapshot: &snapshot })
+                }
+            };
+
+            match text {
+                Ok(text) => {
+                    if let Err(e) = channel.tx_to_websocket.try_send(ChannelMessage::SpecsEvent(text)) {
+                        // 送不出去時下次改送完整快照，避免客戶端狀態不一致
+                        eprintln!("Failed to send view to {}: {}", player.name_id, e);
+                        state.last = None;
+                        continue;
+                    }
+                    state.last = Some(snapshot);
+                }
+                Err(e) => eprintln!("Failed to serialize view for {}: {}", player.name_id, e),
+            }
+        }
+
+        // 本 tick 的戰鬥紀錄送給所有在線玩家，接在本 tick 的畫面之後並佔用下一個序號。
+        // 送不出去時直接丟棄，客戶端會發現序號不連續而要求重新同步
+        if !combat_log.events.is_empty() {
+            for player in (&players).join().filter(|p| p.connected) {
+                if let Some(channel) = websocket_channels.get(&player.name_id) {
+                    let state = view_state.players.entry(player.id).or_default();
+                    state.seq += 1;
+                    if let Some(text) = combat_log.to_message(state.seq) {
+                        let _ = channel.tx_to_websocket.try_send(ChannelMessage::SpecsEvent(text));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+// 相同的種子加上相同的輸入紀錄必須重現相同的對局
+use ac_backend::commands::{InputLog, PlayerInput};
+use ac_backend::config::ServerConfig;
+use ac_backend::headless::{BotMatch, MatchError};
+use std::path::PathBuf;
+
+const SEED: u64 = 7;
+const MAX_ROUNDS: u32 = 100;
+
+fn bot_match() -> BotMatch {
+    // 兩名玩家的對局較短，測試仍涵蓋商店、站位、戰鬥與結算
+    let config = ServerConfig { num_players: 2, ..ServerConfig::default() };
+    let data_dir = PathBuf::from(&config.data_dir);
+    BotMatch::load(&data_dir, config).expect("遊戲資料應該可以載入")
+}
+
+#[test]
+fn replaying_an_input_log_reproduces_the_recorded_match() {
+    let bot_match = bot_match();
+    let (recorded, inputs) = bot_match.run_recorded(SEED, MAX_ROUNDS).expect("電腦對局應該在回合上限內結束");
+    assert_eq!(inputs.seed, SEED);
+    assert_eq!(inputs.data.len(), 1, "開局應該記錄使用的遊戲資料");
+    assert_eq!(inputs.data[0].fingerprint, bot_match.data().fingerprint());
+    assert!(!inputs.records.is_empty(), "電腦玩家應該送出過指令");
+
+    let recorded = serde_json::to_string(&recorded).unwrap();
+    let recorded_inputs = serde_json::to_string(&inputs).unwrap();
+    for _ in 0..2 {
+        let (summary, replayed_inputs) = bot_match.replay(&inputs, MAX_ROUNDS).expect("重現的對局應該結束");
+        assert_eq!(serde_json::to_string(&summary).unwrap(), recorded);
+        assert_eq!(serde_json::to_string(&replayed_inputs).unwrap(), recorded_inputs);
+    }
+}
+
+#[test]
+fn abandoned_players_are_eliminated_before_the_next_pairing() {
+    let bot_match = bot_match();
+    let mut inputs = InputLog::new(SEED);
+    inputs.record_data(0, bot_match.data().fingerprint());
+    inputs.record(1, 1, PlayerInput::Abandoned);
+    let (summary, replayed_inputs) = bot_match.replay(&inputs, MAX_ROUNDS).expect("只剩一位玩家時對局應該結束");
+
+    assert_eq!(summary.winner, Some(0));
+    assert_eq!(summary.combats, 0, "被淘汰的玩家不應該再被配對");
+    let abandoned = summary.placements.iter().find(|p| p.player_id == 1).unwrap();
+    assert_eq!((abandoned.place, abandoned.health, abandoned.eliminated_round), (2, 0, Some(1)));
+    assert_eq!(serde_json::to_string(&replayed_inputs).unwrap(), serde_json::to_string(&inputs).unwrap());
+}
+
+#[test]
+fn replaying_with_different_or_reloaded_game_data_is_refused() {
+    let bot_match = bot_match();
+    let fingerprint = bot_match.data().fingerprint();
+
+    let mut other_data = InputLog::new(SEED);
+    other_data.record_data(0, "0000000000000000".to_string());
+    assert!(matches!(bot_match.replay(&other_data, MAX_ROUNDS), Err(MatchError::DataMismatch { .. })));
+
+    // 對局中途套用過熱更新的資料，只有開局的資料無法重現
+    let mut reloaded = InputLog::new(SEED);
+    reloaded.record_data(0, fingerprint.clone());
+    reloaded.record_data(900, "0000000000000000".to_string());
+    assert!(matches!(bot_match.replay(&reloaded, MAX_ROUNDS), Err(MatchError::DataMismatch { .. })));
+
+    assert!(matches!(bot_match.replay(&InputLog::new(SEED), MAX_ROUNDS), Err(MatchError::DataMismatch { .. })));
+}
@@ -0,0 +1,84 @@
+// 自動排陣在實際戰鬥場地上的效果：雙方的坦克都比自己的遠程棋子更靠近對手
+use ac_backend::commands::Placement;
+use ac_backend::formation::{self, FormationUnit, Role};
+use ac_backend::game_state::arena_position;
+use uuid::Uuid;
+
+fn bench_unit(id: u128, role: Role, value: u32, slot: usize) -> FormationUnit {
+    FormationUnit { id: Uuid::from_u128(id), role, value, placement: Placement::Bench { slot } }
+}
+
+// 一方自動排陣後，各棋子的角色與戰鬥場地座標
+fn arranged_side(side: usize, first_id: u128) -> Vec<(Role, (i32, i32))> {
+    let units = vec![
+        bench_unit(first_id, Role::Ranged, 3, 0),
+        bench_unit(first_id + 1, Role::Tank, 2, 1),
+        bench_unit(first_id + 2, Role::Ranged, 2, 2),
+        bench_unit(first_id + 3, Role::Tank, 1, 3),
+        bench_unit(first_id + 4, Role::Melee, 1, 4),
+    ];
+    formation::arrange(&units, units.len())
+        .into_iter()
+        .filter_map(|(id, placement)| match placement {
+            Placement::Board { x, y } => {
+                let role = units.iter().find(|u| u.id == id).unwrap().role;
+                Some((role, arena_position(side, (x, y))))
+            }
+            Placement::Bench { .. } => None,
+        })
+        .collect()
+}
+
+// 到最近敵方棋子的距離
+fn distance_to_enemy(cell: (i32, i32), enemies: &[(Role, (i32, i32))]) -> f32 {
+    enemies
+        .iter()
+        .map(|(_, (x, y))| (((cell.0 - x).pow(2) + (cell.1 - y).pow(2)) as f32).sqrt())
+        .fold(f32::MAX, f32::min)
+}
+
+#[test]
+fn tanks_stand_closer_to_the_enemy_than_ranged_units_on_both_sides() {
+    let home = arranged_side(0, 0);
+    let away = arranged_side(1, 100);
+    assert_eq!(home.len(), 5);
+    assert_eq!(away.len(), 5);
+
+    for (side, (own, enemies)) in [(&home, &away), (&away, &home)].into_iter().enumerate() {
+        let farthest_tank = own
+            .iter()
+            .filter(|(role, _)| *role == Role::Tank)
+            .map(|(_, cell)| distance_to_enemy(*cell, enemies))
+            .fold(0.0, f32::max);
+        let nearest_ranged = own
+            .iter()
+            .filter(|(role, _)| *role == Role::Ranged)
+            .map(|(_, cell)| distance_to_enemy(*cell, enemies))
+            .fold(f32::MAX, f32::min);
+        assert!(
+            farthest_tank < nearest_ranged,
+            "side {}: tanks up to {} from the enemy, ranged units from {}: {:?}",
+            side,
+            farthest_tank,
+            nearest_ranged,
+            own
+        );
+    }
+}
+
+#[test]
+fn arena_position_round_trips_and_keeps_sides_apart() {
+    use ac_backend::game_state::{board_position, BOARD_HEIGHT, BOARD_WIDTH};
+    for side in 0..2 {
+        for x in 0..BOARD_WIDTH {
+            for y in 0..BOARD_HEIGHT {
+                let arena = arena_position(side, (x, y));
+                assert_eq!(board_position(side, arena), (x, y));
+                let rows = if side == 0 { 0..BOARD_HEIGHT } else { BOARD_HEIGHT..2 * BOARD_HEIGHT };
+                assert!(rows.contains(&arena.1));
+            }
+        }
+    }
+    // 雙方的最前排相鄰
+    assert_eq!(arena_position(1, (0, 0)).1 - arena_position(0, (0, 0)).1, 1);
+}
@@ -0,0 +1,99 @@
+// 技能召喚的位置：不會疊在其他棋子上，也不會放到場地外
+use ac_backend::combat_log::CombatEvent;
+use ac_backend::config::ServerConfig;
+use ac_backend::data::GameData;
+use ac_backend::game_state::{BOARD_HEIGHT, BOARD_WIDTH};
+use ac_backend::simulator::{BattleSimulator, BoardUnit};
+use ac_backend::skills::{SkillContext, SkillExecutor, SkillRegistry};
+use ac_backend::Position;
+use std::path::{Path, PathBuf};
+
+// 在施法者身上召喚三個戰士，再往場地外召喚一個
+struct SummonPile;
+
+impl SkillExecutor for SummonPile {
+    fn execute(&self, ctx: &mut SkillContext) {
+        let at = ctx.position(ctx.caster).unwrap();
+        for _ in 0..3 {
+            ctx.summon("warrior", 1, at.clone());
+        }
+        let off_board: Position = serde_json::from_value(serde_json::json!({ "x": -5, "y": 100 })).unwrap();
+        ctx.summon("warrior", 1, off_board);
+    }
+}
+
+const SUMMONER: &str = r#"[
+    {
+        "id": "summoner",
+        "name": "Summoner",
+        "cost": 1,
+        "stats": { "hp": 100, "attack": 5, "defense": 0, "magic_resist": 0, "attack_speed": 1.0, "attack_range": 1.0, "max_mana": 100 },
+        "skill": { "id": "summon_pile", "damage": 0, "range": 1.0, "requires_mana": false, "cooldown": 100 },
+        "traits": ["human"]
+    }
+]"#;
+
+fn copy_dir(from: &Path, to: &Path) {
+    std::fs::create_dir_all(to).unwrap();
+    for entry in std::fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let target = to.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &target);
+        } else {
+            std::fs::copy(entry.path(), target).unwrap();
+        }
+    }
+}
+
+// 原本的遊戲資料加上召喚師
+fn data_with_summoner(skills: &SkillRegistry) -> GameData {
+    let dir: PathBuf = std::env::temp_dir().join(format!("ac_backend_summon_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    copy_dir(Path::new(&ServerConfig::default().data_dir), &dir);
+    std::fs::write(dir.join("units").join("summoner.json"), SUMMONER).unwrap();
+    let data = GameData::load(&dir, skills);
+    let _ = std::fs::remove_dir_all(&dir);
+    data.expect("遊戲資料應該可以載入")
+}
+
+fn unit(unit_id: &str, position: (i32, i32)) -> BoardUnit {
+    BoardUnit { unit_id: unit_id.to_string(), level: 1, position, items: Vec::new() }
+}
+
+#[test]
+fn summons_are_placed_on_free_cells_inside_the_arena() {
+    let mut skills = SkillRegistry::builtin();
+    skills.register("summon_pile", SummonPile);
+    let data = data_with_summoner(&skills);
+    let simulator = BattleSimulator::new(data, skills, ServerConfig::default());
+
+    let home = [unit("summoner", (3, 0)), unit("tank", (4, 0)), unit("archer", (3, 1))];
+    let away = [unit("warrior", (3, 0))];
+    let report = simulator.run(&home, &away, 1).unwrap();
+    let replay = &report.replay;
+
+    let mut occupied: Vec<(i32, i32)> = replay.units.iter().map(|unit| unit.position).collect();
+    let first_frame = replay
+        .frames
+        .iter()
+        .find(|frame| frame.events.iter().any(|event| matches!(event, CombatEvent::Summoned { .. })))
+        .expect("召喚師應該施放技能");
+    // 召喚發生在戰鬥的第一個 tick，開場的棋子都還在原位
+    assert_eq!(first_frame.tick, 1);
+    let summoned: Vec<(i32, i32)> = first_frame
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            CombatEvent::Summoned { position, .. } => Some(*position),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(summoned.len(), 4);
+    for position in summoned {
+        assert!((0..BOARD_WIDTH).contains(&position.0) && (0..2 * BOARD_HEIGHT).contains(&position.1), "{:?} 在場地外", position);
+        assert!(!occupied.contains(&position), "{:?} 已經有棋子", position);
+        occupied.push(position);
+    }
+}